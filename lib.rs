@@ -3,13 +3,15 @@
 #[ink::contract]
 mod burger_shop {
 
+    use ink::env::call::{build_call, ExecutionInput, Selector};
     use ink::env::debug_println;
-    use ink::prelude::{format, vec::Vec};
+    use ink::env::hash::Blake2x256;
+    use ink::prelude::{string::String, vec::Vec};
     use ink::storage::Mapping;
     use scale::{Decode, Encode};
 
     /// Burger Type sold in the shop
-    #[derive(Debug, Clone, Decode, Encode)]
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
     #[cfg_attr(
         feature = "std",
         derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
@@ -18,20 +20,101 @@ mod burger_shop {
         CheeseBurger,
         ChickenBurger,
         VeggieBurger,
+        Special(u32),
     }
 
     /// Generate an implementation for the order struct
     impl BurgerMenu {
-        /// Designate price for burger variants
+        /// All standard burger variants on the menu
+        fn variants() -> [Self; 3] {
+            [Self::CheeseBurger, Self::ChickenBurger, Self::VeggieBurger]
+        }
+
+        /// Designate default price for burger variants
         fn price(&self) -> Balance {
             match self {
                 Self::CheeseBurger => 12,
                 Self::VeggieBurger => 10,
                 Self::ChickenBurger => 15,
+                // specials are priced through the menu registry
+                Self::Special(_) => 0,
+            }
+        }
+    }
+
+    /// Staff roles that can be granted by the owner
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Role {
+        Cashier,
+        Cook,
+        Manager,
+    }
+
+    /// How fractional discount and tax amounts are rounded
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum RoundingMode {
+        Floor,
+        Ceil,
+        Nearest,
+    }
+
+    /// Generate an implementation for the rounding mode enum
+    impl RoundingMode {
+        /// Divide `value` by `divisor`, rounding the result in this mode
+        fn divide(self, value: Balance, divisor: Balance) -> Balance {
+            match self {
+                Self::Floor => value / divisor,
+                Self::Ceil => value.div_ceil(divisor),
+                Self::Nearest => {
+                    // halves round up
+                    let remainder = value % divisor;
+                    value / divisor + Balance::from(remainder >= divisor - remainder)
+                }
+            }
+        }
+    }
+
+    /// Fixed-size name for menu items, capping their storage cost
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct BoundedName([u8; MAX_NAME_LENGTH]);
+
+    /// Generate an implementation for the bounded name struct
+    impl BoundedName {
+        /// Copy a name into a zero-padded fixed-size buffer
+        fn from_slice(name: &[u8]) -> Result<Self> {
+            if name.len() > MAX_NAME_LENGTH {
+                return Err(BurgerShopError::NameTooLong);
             }
+            let mut bytes = [0u8; MAX_NAME_LENGTH];
+            bytes[..name.len()].copy_from_slice(name);
+            Ok(Self(bytes))
         }
     }
 
+    /// Menu registry entry for burgers added after deployment
+    #[derive(Debug, Clone, Decode, Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct MenuEntry {
+        name: BoundedName,
+        price: Balance,
+        available: bool,
+    }
+
     /// Food sold in the shop
     #[derive(Debug, Clone, Decode, Encode)]
     #[cfg_attr(
@@ -45,14 +128,61 @@ mod burger_shop {
 
     /// Generate an implementation for the fooditem struct
     impl FoodItem {
-        /// Determine price for each food item in shop
-        fn price(&self) -> Balance {
-            match self.burger_menu {
-                BurgerMenu::CheeseBurger => BurgerMenu::CheeseBurger.price() * self.amount as u128,
-                BurgerMenu::ChickenBurger => {
-                    BurgerMenu::ChickenBurger.price() * self.amount as u128
-                }
-                BurgerMenu::VeggieBurger => BurgerMenu::VeggieBurger.price() * self.amount as u128,
+        /// Determine price for each food item in shop from the configured prices
+        fn price(&self, shop: &BurgerShop) -> Result<Balance> {
+            shop.price_of(&self.burger_menu)?
+                .checked_mul(self.amount as u128)
+                .ok_or(BurgerShopError::Overflow)
+        }
+    }
+
+    /// Bundle of items sold together at a fixed price
+    #[derive(Debug, Clone, Decode, Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Combo {
+        items: Vec<FoodItem>,
+        price: Balance,
+    }
+
+    /// Lifecycle stages of an order
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum OrderStatus {
+        Reserved,
+        Paid,
+        Preparing,
+        Ready,
+        Delivered,
+    }
+
+    /// Generate an implementation for the order status enum
+    impl OrderStatus {
+        /// All stages an order can be in
+        fn variants() -> [Self; 5] {
+            [
+                Self::Reserved,
+                Self::Paid,
+                Self::Preparing,
+                Self::Ready,
+                Self::Delivered,
+            ]
+        }
+
+        /// Get the stage that follows the current one, if any
+        fn next(&self) -> Option<Self> {
+            match self {
+                // reservations only move on once fully paid
+                Self::Reserved => None,
+                Self::Paid => Some(Self::Preparing),
+                Self::Preparing => Some(Self::Ready),
+                Self::Ready => Some(Self::Delivered),
+                Self::Delivered => None,
             }
         }
     }
@@ -69,32 +199,155 @@ mod burger_shop {
         total_price: Balance,
         paid: bool,
         order_id: u32,
+        status: OrderStatus,
+        paid_amount: Balance,
+        created_at: Timestamp,
+        payment_token: Option<AccountId>,
+        loyalty_discount: Balance,
+        note: Vec<u8>,
+        tip: Balance,
+        tax: Balance,
+        reservation_expiry: Timestamp,
+        deposit: Balance,
+        deposit_returned: bool,
+        ready_at: Option<Timestamp>,
+        outlet: u32,
+        outlet_order_id: u32,
+        comped: bool,
+        created_at_block: BlockNumber,
+        escrow: Balance,
+        disputed: bool,
+        payer: AccountId,
+        delivered_at: Option<Timestamp>,
+        service_fee: Balance,
+        coupon_percent: u8,
+        item_prices: Vec<Balance>,
     }
 
     /// Generate an implementation for the order struct
     impl Order {
         /// Initialize a new order
-        fn new(list_of_items: Vec<FoodItem>, customer: AccountId, id: u32) -> Self {
-            let total_price = Order::total_price(&list_of_items);
-            Self {
+        fn new(
+            list_of_items: Vec<FoodItem>,
+            customer: AccountId,
+            id: u32,
+            created_at: Timestamp,
+            shop: &BurgerShop,
+        ) -> Result<Self> {
+            let item_prices = Order::item_prices(&list_of_items, shop)?;
+            let total_price = Order::total_price(&list_of_items, shop)?;
+            Ok(Self {
                 list_of_items,
                 customer,
                 total_price,
                 paid: false,
                 order_id: id,
-            }
+                status: OrderStatus::Paid,
+                paid_amount: 0,
+                created_at,
+                payment_token: None,
+                loyalty_discount: 0,
+                note: Vec::new(),
+                tip: 0,
+                tax: 0,
+                reservation_expiry: 0,
+                deposit: 0,
+                deposit_returned: false,
+                ready_at: None,
+                outlet: MAIN_OUTLET,
+                outlet_order_id: 0,
+                comped: false,
+                created_at_block: 0,
+                escrow: 0,
+                disputed: false,
+                payer: customer,
+                delivered_at: None,
+                service_fee: 0,
+                coupon_percent: 0,
+                item_prices,
+            })
         }
 
         /// Get total price of the food items in the order book
-        fn total_price(list_of_items: &Vec<FoodItem>) -> Balance {
+        fn total_price(list_of_items: &Vec<FoodItem>, shop: &BurgerShop) -> Result<Balance> {
             let mut total = 0;
             for item in list_of_items {
-                total += item.price();
+                total = item
+                    .price(shop)?
+                    .checked_add(total)
+                    .ok_or(BurgerShopError::Overflow)?;
+            }
+            Ok(total)
+        }
+
+        /// Get the price of each line of food items at current prices
+        fn item_prices(list_of_items: &[FoodItem], shop: &BurgerShop) -> Result<Vec<Balance>> {
+            list_of_items.iter().map(|item| item.price(shop)).collect()
+        }
+
+        /// Split the total price across the order's lines in proportion to what
+        /// each line cost when it was ordered, giving any rounding remainder to
+        /// the last line
+        fn line_totals(&self) -> Result<Vec<Balance>> {
+            let gross = self
+                .item_prices
+                .iter()
+                .try_fold(0, |total: Balance, price| total.checked_add(*price))
+                .ok_or(BurgerShopError::Overflow)?;
+
+            let mut remaining = self.total_price;
+            let mut totals = Vec::new();
+            for (i, price) in self.item_prices.iter().enumerate() {
+                let share = if i + 1 == self.item_prices.len() {
+                    remaining
+                } else {
+                    price
+                        .checked_mul(self.total_price)
+                        .ok_or(BurgerShopError::Overflow)?
+                        .checked_div(gross)
+                        .unwrap_or(0)
+                };
+                remaining -= share;
+                totals.push(share);
             }
-            total
+            Ok(totals)
+        }
+
+        /// Get the full amount the customer owes, in menu price units
+        fn amount_due(&self) -> Result<Balance> {
+            self.total_price
+                .checked_add(self.tip)
+                .and_then(|due| due.checked_add(self.tax))
+                .and_then(|due| due.checked_add(self.deposit))
+                .ok_or(BurgerShopError::Overflow)
         }
     }
 
+    /// Optional extras a customer can attach when placing an order
+    #[derive(Default)]
+    struct OrderOptions {
+        coupon: Option<Vec<u8>>,
+        note: Vec<u8>,
+        tip: Balance,
+        fixed_price: Option<Balance>,
+        client_ref: Option<Vec<u8>>,
+        ready_at: Option<Timestamp>,
+        outlet: u32,
+        customer: Option<AccountId>,
+    }
+
+    /// Snapshot of the shop's tunable parameters
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct ShopConfig {
+        price_multiplier: Balance,
+        tax_basis_points: u16,
+        min_order_value: Balance,
+        max_items_per_order: u32,
+        paused: bool,
+        owner: AccountId,
+    }
+
     /// Generate Events For Contract
     /// Transfer event, for when a transfer occurs.
     #[ink(event)]
@@ -106,6 +359,16 @@ mod burger_shop {
         value: Balance,
     }
 
+    /// OrderCreated Event, gets emitted when a customer places an order
+    #[ink(event)]
+    pub struct OrderCreated {
+        #[ink(topic)]
+        order_id: u32,
+        #[ink(topic)]
+        customer: AccountId,
+        total_price: Balance,
+    }
+
     /// GetAllOrders Events, get emitted when the owner gets all orders in storage
     #[ink(event)]
     pub struct GetAllOrders {
@@ -120,11 +383,58 @@ mod burger_shop {
         single_order: Order,
     }
 
-    /// CreatedShopAndStorage
+    /// OrderRemoved Event, gets emitted when an order is deleted from storage
     #[ink(event)]
-    pub struct CreatedShopAndStorage {
+    pub struct OrderRemoved {
         #[ink(topic)]
-        orders: Vec<(u32, Order)>,
+        order_id: u32,
+        #[ink(topic)]
+        customer: AccountId,
+    }
+
+    /// Refunded Event, gets emitted when money from an order goes back out
+    #[ink(event)]
+    pub struct Refunded {
+        #[ink(topic)]
+        order_id: u32,
+        #[ink(topic)]
+        customer: AccountId,
+        amount: Balance,
+    }
+
+    /// LowStockAlert Event, gets emitted when an order takes a burger's stock down
+    /// to its reorder point
+    #[ink(event)]
+    pub struct LowStockAlert {
+        #[ink(topic)]
+        outlet: u32,
+        menu: BurgerMenu,
+        remaining: u32,
+    }
+
+    /// OrderStatusChanged Event, gets emitted when an order moves to a new stage
+    #[ink(event)]
+    pub struct OrderStatusChanged {
+        #[ink(topic)]
+        order_id: u32,
+        status: OrderStatus,
+    }
+
+    /// OwnershipTransferred Event, gets emitted when the shop changes hands
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous: AccountId,
+        #[ink(topic)]
+        new: AccountId,
+    }
+
+    /// ShopInitialized Event, gets emitted once when the shop is deployed
+    #[ink(event)]
+    pub struct ShopInitialized {
+        #[ink(topic)]
+        owner: AccountId,
+        price_multiplier: Balance,
     }
 
     /// Handle Errors that happens during operations
@@ -134,16 +444,193 @@ mod burger_shop {
     pub enum BurgerShopError {
         PaymentError,
         OrderNotCompleted,
+        CallerIsShop,
+        EmptyOrder,
+        AlreadyPaid,
+        IncorrectPayment {
+            expected: Balance,
+            received: Balance,
+        },
+        NotCancellable,
+        OrderNotFound,
+        InvalidStatusTransition,
+        NotOwner,
+        UnknownMenuItem,
+        InsufficientBalance,
+        InvalidMultiplier,
+        Overflow,
+        OutOfStock {
+            menu: BurgerMenu,
+        },
+        InvalidCoupon,
+        Overpaid,
+        NotFullyPaid,
+        TokenTransferFailed,
+        InsufficientPoints,
+        ZeroAddress,
+        ShopPaused,
+        OrderTooLarge,
+        NoteTooLong,
+        NotOwnerOfOrder,
+        Unauthorized,
+        RefundExceedsTotal,
+        NameTooLong,
+        NotPaused,
+        InvalidRange,
+        BelowMinimum,
+        UpgradeFailed,
+        InvalidHappyHour,
+        DepositAlreadyReturned,
+        NotAllowlisted,
+        DailyLimitReached,
+        UnknownCombo,
+        InvalidFee,
+        RefundRateLimited,
+        InvalidSchedule,
+        ItemUnavailable,
+        UnknownOutlet,
+        OrderComped,
+        IdSpaceExhausted,
+        StillEscrowed,
+        NotEscrowed,
+        OrderDisputed,
+        ZeroAmountItem,
+        QuantityTooHigh,
+        RefundsDisabled,
+        Banned,
+        ZeroDeposit,
+        SplitOrder,
+    }
+
+    /// Errors returned by a PSP22 token contract
+    #[derive(Debug, PartialEq, Eq, Encode, Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Psp22Error {
+        Custom(String),
+        InsufficientBalance,
+        InsufficientAllowance,
+        ZeroRecipientAddress,
+        ZeroSenderAddress,
+        SafeTransferCheckFailed(String),
     }
 
     /// Result type
     pub type Result<T> = core::result::Result<T, BurgerShopError>;
 
+    /// A customer paired with a client reference they chose for an order
+    type ClientRef = (AccountId, Vec<u8>);
+
+    /// Revenue attributed to each burger in an order
+    type ItemRevenue = Vec<(BurgerMenu, Balance)>;
+
+    /// Default conversion factor between menu prices and on-chain value
+    const DEFAULT_PRICE_MULTIPLIER: Balance = 1_000_000_000_000;
+
+    /// Default cap on the number of burgers in a single order
+    const DEFAULT_MAX_ITEMS_PER_ORDER: u32 = 100;
+
+    /// Number of milliseconds in a day
+    const MILLIS_PER_DAY: Timestamp = 24 * 60 * 60 * 1000;
+
+    /// Default time in milliseconds a reservation is held before it expires
+    const DEFAULT_RESERVATION_TTL: Timestamp = MILLIS_PER_DAY;
+
+    /// Number of seconds in a day
+    const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+    /// Maximum length in bytes of a menu item name
+    const MAX_NAME_LENGTH: usize = 32;
+
+    /// Maximum length in bytes of an order note
+    const MAX_NOTE_LENGTH: usize = 256;
+
+    /// Loyalty points needed for one unit of discount
+    const POINTS_PER_DISCOUNT_UNIT: u128 = 100;
+
+    /// Denominator for rates expressed in basis points
+    const BASIS_POINTS: Balance = 10_000;
+
+    /// Maximum number of orders returned by a single page
+    const MAX_PAGE_SIZE: u32 = 50;
+
+    /// Maximum number of orders returned by the top orders query
+    const MAX_TOP_ORDERS: u32 = 20;
+
+    /// Version of the contract logic, bumped on every deployed revision
+    const VERSION: u32 = 1;
+
+    /// Id of the outlet registered when the shop is deployed
+    const MAIN_OUTLET: u32 = 0;
+
     /// Contract storage for storing burger shop data
     #[ink(storage)]
     pub struct BurgerShop {
-        orders: Vec<(u32, Order)>,
         orders_mapping: Mapping<u32, Order>,
+        order_ids: Vec<u32>,
+        next_order_id: u32,
+        owner: AccountId,
+        prices: Mapping<BurgerMenu, Balance>,
+        menu_items: Mapping<u32, MenuEntry>,
+        next_menu_item_id: u32,
+        customer_orders: Mapping<AccountId, Vec<u32>>,
+        price_multiplier: Balance,
+        stock: Mapping<(u32, BurgerMenu), u32>,
+        total_revenue: Balance,
+        coupons: Mapping<Vec<u8>, u8>,
+        order_count: u32,
+        payment_token: Option<AccountId>,
+        loyalty_points: Mapping<AccountId, u128>,
+        points_per_unit: u128,
+        loyalty_discounts: Mapping<AccountId, Balance>,
+        paused: bool,
+        max_items_per_order: u32,
+        total_tips: Balance,
+        status_orders: Mapping<OrderStatus, Vec<u32>>,
+        roles: Mapping<AccountId, Role>,
+        tax_basis_points: u16,
+        total_tax: Balance,
+        reservation_ttl: Timestamp,
+        min_order_value: Balance,
+        customer_spend: Mapping<AccountId, Balance>,
+        happy_hour_start: u32,
+        happy_hour_end: u32,
+        happy_hour_percent: u8,
+        container_deposit: Balance,
+        allowlist_enabled: bool,
+        allowlist: Mapping<AccountId, ()>,
+        max_orders_per_day: u32,
+        daily_orders: Mapping<(AccountId, u64), u32>,
+        combos: Mapping<u32, Combo>,
+        next_combo_id: u32,
+        fee_recipient: AccountId,
+        fee_basis_points: u16,
+        client_refs: Mapping<ClientRef, u32>,
+        split_shares: Mapping<(u32, AccountId), Balance>,
+        contributions: Mapping<(u32, AccountId), Balance>,
+        split_payers: Mapping<u32, Vec<AccountId>>,
+        max_refund_per_block: Balance,
+        refund_block: BlockNumber,
+        refunded_in_block: Balance,
+        availability: Mapping<BurgerMenu, bool>,
+        item_revenue: Mapping<(u32, BurgerMenu), Balance>,
+        order_item_revenue: Mapping<u32, ItemRevenue>,
+        rounding_mode: RoundingMode,
+        outlets: Mapping<u32, BoundedName>,
+        next_outlet_id: u32,
+        outlet_revenue: Mapping<u32, Balance>,
+        outlet_orders: Mapping<(u32, u32), u32>,
+        next_outlet_order_ids: Mapping<u32, u32>,
+        cancel_grace_period: Timestamp,
+        cancellation_fee_basis_points: u16,
+        escrowed: Balance,
+        max_quantity_per_item: u32,
+        status_counts: Mapping<OrderStatus, u32>,
+        reorder_points: Mapping<(u32, BurgerMenu), u32>,
+        refunds_enabled: bool,
+        surge_basis_points_per_pending: u16,
+        max_surge_basis_points: u32,
+        blacklist: Mapping<AccountId, ()>,
+        sales_count: u32,
     }
 
     /// Implements Burgershop contract storage struct
@@ -151,127 +638,6698 @@ mod burger_shop {
         /// Initialize the burgershop with default/empty values
         #[ink(constructor)]
         pub fn new() -> Self {
-            let order_storage_vector: Vec<(u32, Order)> = Vec::new();
+            Self::init(DEFAULT_PRICE_MULTIPLIER)
+        }
+
+        /// Initialize the burgershop with a custom price to on-chain value multiplier
+        #[ink(constructor)]
+        pub fn new_with_multiplier(price_multiplier: Balance) -> Result<Self> {
+            if price_multiplier == 0 {
+                return Err(BurgerShopError::InvalidMultiplier);
+            }
+            Ok(Self::init(price_multiplier))
+        }
+
+        /// Initialize the burgershop to take payments in a PSP22 token
+        #[ink(constructor)]
+        pub fn new_with_payment_token(payment_token: AccountId) -> Self {
+            let mut shop = Self::init(DEFAULT_PRICE_MULTIPLIER);
+            shop.payment_token = Some(payment_token);
+            shop
+        }
+
+        /// Initialize the burgershop with custom prices and initial stock for the
+        /// standard burgers, using the defaults for any not listed. Specials are
+        /// ignored since the menu registry starts out empty.
+        #[ink(constructor)]
+        pub fn new_with_menu(items: Vec<(BurgerMenu, Balance, u32)>) -> Self {
+            let mut shop = Self::init(DEFAULT_PRICE_MULTIPLIER);
+            for (menu, price, stock) in items {
+                if let BurgerMenu::Special(_) = menu {
+                    continue;
+                }
+                shop.prices.insert(&menu, &price);
+                shop.stock.insert((MAIN_OUTLET, menu), &stock);
+            }
+            shop
+        }
+
+        /// Build the initial contract storage
+        fn init(price_multiplier: Balance) -> Self {
             let order_storage_mapping = Mapping::new();
 
+            // Seed the price list with the default prices
+            let mut prices = Mapping::new();
+            for menu in BurgerMenu::variants() {
+                prices.insert(&menu, &menu.price());
+            }
+
+            // Register the main outlet
+            let mut outlets = Mapping::new();
+            outlets.insert(
+                MAIN_OUTLET,
+                &BoundedName::from_slice(b"main").expect("name fits"),
+            );
+
+            // Emit event
+            let owner = Self::env().caller();
+            Self::env().emit_event(ShopInitialized {
+                owner,
+                price_multiplier,
+            });
+
             Self {
-                orders: order_storage_vector,
                 orders_mapping: order_storage_mapping,
+                order_ids: Vec::new(),
+                next_order_id: 0,
+                owner,
+                prices,
+                menu_items: Mapping::new(),
+                next_menu_item_id: 0,
+                customer_orders: Mapping::new(),
+                price_multiplier,
+                stock: Mapping::new(),
+                total_revenue: 0,
+                coupons: Mapping::new(),
+                order_count: 0,
+                payment_token: None,
+                loyalty_points: Mapping::new(),
+                points_per_unit: 1,
+                loyalty_discounts: Mapping::new(),
+                paused: false,
+                max_items_per_order: DEFAULT_MAX_ITEMS_PER_ORDER,
+                total_tips: 0,
+                status_orders: Mapping::new(),
+                roles: Mapping::new(),
+                tax_basis_points: 0,
+                total_tax: 0,
+                reservation_ttl: DEFAULT_RESERVATION_TTL,
+                min_order_value: 0,
+                customer_spend: Mapping::default(),
+                happy_hour_start: 0,
+                happy_hour_end: 0,
+                happy_hour_percent: 0,
+                container_deposit: 0,
+                allowlist_enabled: false,
+                allowlist: Mapping::default(),
+                max_orders_per_day: u32::MAX,
+                daily_orders: Mapping::default(),
+                combos: Mapping::default(),
+                next_combo_id: 0,
+                fee_recipient: owner,
+                fee_basis_points: 0,
+                client_refs: Mapping::default(),
+                split_shares: Mapping::default(),
+                contributions: Mapping::default(),
+                split_payers: Mapping::default(),
+                max_refund_per_block: Balance::MAX,
+                refund_block: 0,
+                refunded_in_block: 0,
+                availability: Mapping::default(),
+                item_revenue: Mapping::default(),
+                order_item_revenue: Mapping::default(),
+                rounding_mode: RoundingMode::Floor,
+                outlets,
+                next_outlet_id: MAIN_OUTLET + 1,
+                outlet_revenue: Mapping::default(),
+                outlet_orders: Mapping::default(),
+                next_outlet_order_ids: Mapping::default(),
+                cancel_grace_period: 0,
+                cancellation_fee_basis_points: 0,
+                escrowed: 0,
+                max_quantity_per_item: u32::MAX,
+                status_counts: Mapping::default(),
+                reorder_points: Mapping::default(),
+                refunds_enabled: true,
+                surge_basis_points_per_pending: 0,
+                max_surge_basis_points: 0,
+                blacklist: Mapping::default(),
+                sales_count: 0,
             }
         }
 
         /// Take order and make payment
         #[ink(message, payable)]
         pub fn take_order_and_payment(&mut self, list_of_items: Vec<FoodItem>) -> Result<Order> {
-            // Get the caller account id
-            let caller = Self::env().caller();
+            self.place_order(list_of_items, OrderOptions::default())
+        }
 
-            // Assert the user is valid
-            assert!(
-                caller != self.env().account_id(),
-                "You are not the customer!"
-            );
+        /// Take order and make payment at one of the shop's outlets
+        #[ink(message, payable)]
+        pub fn take_order_and_payment_at(
+            &mut self,
+            outlet: u32,
+            list_of_items: Vec<FoodItem>,
+        ) -> Result<Order> {
+            self.place_order(
+                list_of_items,
+                OrderOptions {
+                    outlet,
+                    ..Default::default()
+                },
+            )
+        }
+
+        /// Take order and make payment, applying a discount coupon if one is given
+        #[ink(message, payable)]
+        pub fn take_order_and_payment_with_coupon(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+            coupon: Option<Vec<u8>>,
+        ) -> Result<Order> {
+            self.place_order(
+                list_of_items,
+                OrderOptions {
+                    coupon,
+                    ..Default::default()
+                },
+            )
+        }
 
-            // assert the order contains at least 1 item
-            assert!(list_of_items.len() as u32 > 0, "Can't take an empty order!");
+        /// Take order and make payment, adding a tip on top of the order total
+        #[ink(message, payable)]
+        pub fn take_order_and_payment_with_tip(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+            tip: Balance,
+        ) -> Result<Order> {
+            self.place_order(
+                list_of_items,
+                OrderOptions {
+                    tip,
+                    ..Default::default()
+                },
+            )
+        }
 
-            // Generate local id
-            let id = self.orders.len() as u32;
+        /// Take order and make payment, attaching special instructions to the order
+        #[ink(message, payable)]
+        pub fn take_order_and_payment_with_note(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+            note: Vec<u8>,
+        ) -> Result<Order> {
+            self.place_order(
+                list_of_items,
+                OrderOptions {
+                    note,
+                    ..Default::default()
+                },
+            )
+        }
 
-            // Calculate and set order price
-            let total_price = Order::total_price(&list_of_items);
-            let mut order = Order::new(list_of_items, caller, id);
-            order.total_price = total_price;
+        /// Take an order and its payment, returning the existing order instead if
+        /// the caller already placed one with the same client reference
+        #[ink(message, payable)]
+        pub fn take_order_and_payment_with_ref(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+            client_ref: Vec<u8>,
+        ) -> Result<Order> {
+            self.place_order(
+                list_of_items,
+                OrderOptions {
+                    client_ref: Some(client_ref),
+                    ..Default::default()
+                },
+            )
+        }
 
-            // assert that the order hasn't been paid for already
-            assert!(
-                order.paid == false,
-                "Can't pay for an order that is paid for already",
-            );
+        /// Take an order for a combo meal at the combo's fixed price
+        #[ink(message, payable)]
+        pub fn take_combo_order(&mut self, combo_id: u32) -> Result<Order> {
+            let combo = self
+                .combos
+                .get(combo_id)
+                .ok_or(BurgerShopError::UnknownCombo)?;
+
+            self.place_order(
+                combo.items,
+                OrderOptions {
+                    fixed_price: Some(combo.price),
+                    ..Default::default()
+                },
+            )
+        }
+
+        /// Take a paid pre-order to be ready for pickup at a later time
+        #[ink(message, payable)]
+        pub fn take_preorder(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+            ready_at: Timestamp,
+        ) -> Result<Order> {
+            if ready_at <= self.env().block_timestamp() {
+                return Err(BurgerShopError::InvalidSchedule);
+            }
+
+            self.place_order(
+                list_of_items,
+                OrderOptions {
+                    ready_at: Some(ready_at),
+                    ..Default::default()
+                },
+            )
+        }
+
+        /// Place a fresh order for the same items as one of the caller's previous
+        /// orders, priced at current prices
+        #[ink(message, payable)]
+        pub fn reorder(&mut self, previous_id: u32) -> Result<Order> {
+            let previous = self
+                .orders_mapping
+                .get(previous_id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if self.env().caller() != previous.customer {
+                return Err(BurgerShopError::NotOwnerOfOrder);
+            }
+
+            self.place_order(
+                previous.list_of_items,
+                OrderOptions {
+                    outlet: previous.outlet,
+                    ..Default::default()
+                },
+            )
+        }
+
+        /// Take several orders paid for with a single payment. If the payment doesn't
+        /// cover all of them, none are created.
+        #[ink(message, payable)]
+        pub fn take_orders_batch(&mut self, orders: Vec<Vec<FoodItem>>) -> Result<Vec<Order>> {
+            if orders.is_empty() {
+                return Err(BurgerShopError::EmptyOrder);
+            }
 
-            let multiply: Balance = 1_000_000_000_000;
             let transferred_val = self.env().transferred_value();
+            let mut expected_val: Balance = 0;
+            let mut created = Vec::new();
 
-            // assert the value sent == total_price
-            assert!(
-                transferred_val
-                    == order
-                        .total_price
-                        .checked_mul(multiply)
-                        .expect("Overflow!!!"),
-                "{}",
-                format!("Please pay complete amount which is {}", order.total_price)
-            );
+            // create each order in turn so stock and ids carry over between them
+            for list_of_items in orders {
+                let (mut order, remaining_stock) =
+                    self.prepare_order(list_of_items, OrderOptions::default())?;
+                let amount = self.chain_price(order.amount_due()?)?;
+                expected_val = expected_val
+                    .checked_add(amount)
+                    .ok_or(BurgerShopError::Overflow)?;
 
-            // print total price
-            debug_println!("Expected value: {}", order.total_price);
+                order.paid = true;
+                order.paid_amount = amount;
+                self.settle(&mut order)?;
+                self.insert_order(&order, remaining_stock);
+                created.push(order);
+            }
 
-            // print transferred_val
-            debug_println!(
-                "Expected received payment without conversion: {}",
-                transferred_val
-            );
+            // returning an error reverts every order created above
+            if transferred_val < expected_val {
+                return Err(BurgerShopError::IncorrectPayment {
+                    expected: expected_val,
+                    received: transferred_val,
+                });
+            }
 
-            // make payment
-            match self
-                .env()
-                .transfer(self.env().account_id(), order.total_price)
-            {
-                Ok(_) => {
-                    // get current length of the list orders in storage
-                    let id = self.orders.len() as u32;
-                    order.paid = true;
+            // hand back anything paid above the combined total
+            let caller = self.env().caller();
+            let change = transferred_val - expected_val;
+            if change > 0 {
+                if self.env().transfer(caller, change).is_err() {
+                    return Err(BurgerShopError::PaymentError);
+                }
 
-                    // Emit event
-                    self.env().emit_event(Transfer {
-                        from: Some(order.customer),
-                        to: Some(self.env().account_id()),
-                        value: order.total_price,
+                // Emit event
+                self.env().emit_event(Transfer {
+                    from: Some(self.env().account_id()),
+                    to: Some(caller),
+                    value: change,
+                });
+                if let Some(last) = created.last() {
+                    self.env().emit_event(Refunded {
+                        order_id: last.order_id,
+                        customer: caller,
+                        amount: change,
                     });
-
-                    // push to storage
-                    self.orders_mapping.insert(id, &order);
-                    self.orders.push((id, order.clone()));
-                    Ok(order)
                 }
-                Err(_) => Err(BurgerShopError::PaymentError),
             }
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(caller),
+                to: Some(self.env().account_id()),
+                value: expected_val,
+            });
+
+            Ok(created)
         }
 
-        /// Get a single order from storage
-        #[ink(message)]
-        pub fn get_single_order(&self, id: u32) -> Order {
-            // get single order
-            let order = self.orders_mapping.get(id).expect("Order not found");
+        /// Take order and pull its payment in the shop's PSP22 token, falling back
+        /// to native payment when no token is configured
+        #[ink(message, payable)]
+        pub fn take_order_and_payment_psp22(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+        ) -> Result<Order> {
+            let token = match self.payment_token {
+                Some(token) => token,
+                None => return self.place_order(list_of_items, OrderOptions::default()),
+            };
 
-            // emit event
-            self.env().emit_event(GetSingleOrder {
-                single_order: order.clone(),
+            let (mut order, remaining_stock) =
+                self.prepare_order(list_of_items, OrderOptions::default())?;
+
+            // the token pays for the order, not native value
+            let transferred_val = self.env().transferred_value();
+            if transferred_val > 0 {
+                return Err(BurgerShopError::IncorrectPayment {
+                    expected: 0,
+                    received: transferred_val,
+                });
+            }
+
+            // pull payment from the customer
+            let amount = self.chain_price(order.amount_due()?)?;
+            self.token_transfer_from(token, order.customer, amount)?;
+            order.paid = true;
+            order.paid_amount = amount;
+            order.payment_token = Some(token);
+
+            // an error from here on reverts the whole call, token pull included
+            self.settle(&mut order)?;
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(order.customer),
+                to: Some(self.env().account_id()),
+                value: amount,
             });
 
-            // return order
-            order
+            // push to storage
+            self.insert_order(&order, remaining_stock);
+
+            Ok(order)
         }
 
-        /// Get the orders in the storage
+        /// Give a customer a free order on the house. Comped orders are marked paid
+        /// but don't count towards revenue.
         #[ink(message)]
-        pub fn get_orders(&self) -> Option<Vec<(u32, Order)>> {
-            // Get all orders
-            let get_all_orders = &self.orders;
+        pub fn comp_order(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+            customer: AccountId,
+        ) -> Result<Order> {
+            self.ensure_role(&[Role::Manager])?;
+            Self::ensure_not_zero(customer)?;
 
-            if get_all_orders.len() > 0 {
-                let myorders: Vec<(u32, Order)> = get_all_orders.to_vec();
+            let (mut order, remaining_stock) = self.prepare_order(
+                list_of_items,
+                OrderOptions {
+                    customer: Some(customer),
+                    ..Default::default()
+                },
+            )?;
 
-                // Emit events
-                self.env().emit_event(GetAllOrders {
-                    orders: myorders.clone(),
-                });
+            // the shop covers the whole order, so leave the customer's
+            // redeemed points for a paid one
+            order.total_price += order.loyalty_discount;
+            order.loyalty_discount = 0;
+            order.tax = 0;
+            order.deposit = 0;
+            order.paid = true;
+            order.comped = true;
 
-                // converts reference to an owned/new vector
-                Some(myorders)
-            } else {
-                None
+            // push to storage
+            self.insert_order(&order, remaining_stock);
+
+            Ok(order)
+        }
+
+        /// Reserve an order with a deposit, leaving the balance to be paid later
+        #[ink(message, payable)]
+        pub fn deposit_and_reserve(&mut self, list_of_items: Vec<FoodItem>) -> Result<Order> {
+            let (mut order, remaining_stock) =
+                self.prepare_order(list_of_items, OrderOptions::default())?;
+
+            // a full payment should go through take_order_and_payment
+            let deposit = self.env().transferred_value();
+            if deposit == 0 {
+                return Err(BurgerShopError::ZeroDeposit);
+            }
+            if deposit >= self.chain_price(order.amount_due()?)? {
+                return Err(BurgerShopError::Overpaid);
+            }
+            order.status = OrderStatus::Reserved;
+            order.paid_amount = deposit;
+            order.reservation_expiry = order.created_at.saturating_add(self.reservation_ttl);
+
+            // remember who paid the deposit, for refunds
+            self.record_payment(order.order_id, order.customer, deposit)?;
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(order.customer),
+                to: Some(self.env().account_id()),
+                value: deposit,
+            });
+
+            // push to storage
+            self.insert_order(&order, remaining_stock);
+
+            Ok(order)
+        }
+
+        /// Pay towards the balance of a reserved order, refunding any overpayment
+        #[ink(message, payable)]
+        pub fn pay_balance(&mut self, id: u32) -> Result<Order> {
+            let caller = self.env().caller();
+
+            // get the reserved order
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if order.paid {
+                return Err(BurgerShopError::AlreadyPaid);
+            }
+            // each payer's share of a split order goes through contribute
+            if self.is_split_order(id) {
+                return Err(BurgerShopError::SplitOrder);
             }
+
+            let transferred_val = self.env().transferred_value();
+            let expected_val = self.chain_price(order.amount_due()?)?;
+            let paid_amount = order
+                .paid_amount
+                .checked_add(transferred_val)
+                .ok_or(BurgerShopError::Overflow)?;
+
+            // hand back anything above the remaining balance
+            let change = paid_amount.saturating_sub(expected_val);
+            if change > 0 {
+                if self.env().transfer(caller, change).is_err() {
+                    return Err(BurgerShopError::PaymentError);
+                }
+
+                // Emit event
+                self.env().emit_event(Refunded {
+                    order_id: id,
+                    customer: caller,
+                    amount: change,
+                });
+            }
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(caller),
+                to: Some(self.env().account_id()),
+                value: transferred_val - change,
+            });
+
+            // remember who paid towards the balance, for refunds
+            self.record_payment(id, caller, transferred_val - change)?;
+            order.payer = caller;
+
+            order.paid_amount = paid_amount - change;
+            if order.paid_amount == expected_val {
+                order.paid = true;
+                self.set_status(&mut order, OrderStatus::Paid);
+
+                // hold the payment in escrow
+                self.settle(&mut order)?;
+            }
+
+            // update storage
+            self.orders_mapping.insert(id, &order);
+
+            Ok(order)
+        }
+
+        /// Replace the items of a reserved order that hasn't been fully paid yet
+        #[ink(message)]
+        pub fn update_order_items(
+            &mut self,
+            id: u32,
+            list_of_items: Vec<FoodItem>,
+        ) -> Result<Order> {
+            // get the reserved order
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if self.env().caller() != order.customer {
+                return Err(BurgerShopError::NotOwnerOfOrder);
+            }
+            if order.paid {
+                return Err(BurgerShopError::AlreadyPaid);
+            }
+            // the payers' shares were agreed against the old total
+            if self.is_split_order(id) {
+                return Err(BurgerShopError::SplitOrder);
+            }
+            let list_of_items = Self::merge_items(list_of_items)?;
+            self.validate_items(&list_of_items)?;
+
+            // swap the reserved stock over to the new items
+            self.restore_stock(order.outlet, &order.list_of_items);
+            for (menu, level) in self.remaining_stock(order.outlet, &list_of_items)? {
+                self.stock.insert((order.outlet, menu), &level);
+            }
+
+            // Reprice the order the way it was first charged, giving back the
+            // loyalty discount redeemed against the old items. The order itself
+            // doesn't count towards the surge.
+            let base_price = Order::total_price(&list_of_items, self)?;
+            self.ensure_min_order_value(base_price)?;
+            let loyalty_balance = self
+                .loyalty_discounts
+                .get(order.customer)
+                .unwrap_or(0)
+                .checked_add(order.loyalty_discount)
+                .ok_or(BurgerShopError::Overflow)?;
+            (order.total_price, order.loyalty_discount) = self.price_items(
+                base_price,
+                order.coupon_percent,
+                loyalty_balance,
+                self.pending_orders().saturating_sub(1),
+            )?;
+            order.tax = self.tax_on(order.total_price)?;
+            order.item_prices = Order::item_prices(&list_of_items, self)?;
+            order.list_of_items = list_of_items;
+
+            // the deposit already paid must stay below the new total
+            if order.paid_amount >= self.chain_price(order.amount_due()?)? {
+                return Err(BurgerShopError::Overpaid);
+            }
+
+            // update storage
+            self.loyalty_discounts
+                .insert(order.customer, &(loyalty_balance - order.loyalty_discount));
+            self.orders_mapping.insert(id, &order);
+
+            Ok(order)
+        }
+
+        /// Refund part of a paid order, e.g. when an item can't be delivered
+        #[ink(message)]
+        pub fn partial_refund(&mut self, id: u32, refund_amount: Balance) -> Result<()> {
+            self.ensure_role(&[Role::Cashier, Role::Manager])?;
+            self.ensure_refunds_enabled()?;
+
+            // get the order to refund
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if !order.paid {
+                return Err(BurgerShopError::NotFullyPaid);
+            }
+            if order.comped {
+                return Err(BurgerShopError::OrderComped);
+            }
+            if refund_amount > order.total_price {
+                return Err(BurgerShopError::RefundExceedsTotal);
+            }
+
+            // refund payment
+            let refund = self.chain_price(refund_amount)?;
+            self.refund(&order, order.customer, refund)?;
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(self.env().account_id()),
+                to: Some(order.customer),
+                value: refund,
+            });
+            self.env().emit_event(Refunded {
+                order_id: id,
+                customer: order.customer,
+                amount: refund,
+            });
+
+            // the refunded part comes out of escrow, or else no longer counts
+            // as revenue
+            if order.escrow > 0 {
+                self.drop_escrow(&mut order, refund);
+            } else {
+                self.refund_item_revenue(&order, refund_amount);
+                self.reduce_revenue(order.outlet, refund_amount);
+                self.reduce_spend(order.customer, refund_amount);
+            }
+            order.total_price -= refund_amount;
+            order.paid_amount = order.paid_amount.saturating_sub(refund);
+
+            // update storage
+            self.orders_mapping.insert(id, &order);
+
+            Ok(())
+        }
+
+        /// Get the total a customer has spent on completed orders, net of refunds
+        #[ink(message)]
+        pub fn customer_spend(&self, who: AccountId) -> Balance {
+            self.customer_spend.get(who).unwrap_or(0)
+        }
+
+        /// Refund the container deposit on a paid order once its packaging is returned
+        #[ink(message)]
+        pub fn return_container(&mut self, id: u32) -> Result<()> {
+            self.ensure_role(&[Role::Cashier, Role::Manager])?;
+
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if !order.paid {
+                return Err(BurgerShopError::NotFullyPaid);
+            }
+            if order.deposit_returned {
+                return Err(BurgerShopError::DepositAlreadyReturned);
+            }
+            if order.escrow > 0 {
+                return Err(BurgerShopError::StillEscrowed);
+            }
+
+            // refund the deposit
+            let refund = self.chain_price(order.deposit)?;
+            if refund > 0 {
+                self.refund(&order, order.customer, refund)?;
+
+                // Emit event
+                self.env().emit_event(Transfer {
+                    from: Some(self.env().account_id()),
+                    to: Some(order.customer),
+                    value: refund,
+                });
+                self.env().emit_event(Refunded {
+                    order_id: id,
+                    customer: order.customer,
+                    amount: refund,
+                });
+            }
+
+            // the returned deposit no longer counts as revenue
+            order.deposit_returned = true;
+            order.paid_amount = order.paid_amount.saturating_sub(refund);
+            self.reduce_revenue(order.outlet, order.deposit);
+            self.reduce_spend(order.customer, order.deposit);
+
+            // update storage
+            self.orders_mapping.insert(id, &order);
+
+            Ok(())
+        }
+
+        /// Get the loyalty points held by an account
+        #[ink(message)]
+        pub fn points_of(&self, who: AccountId) -> u128 {
+            self.loyalty_points.get(who).unwrap_or(0)
+        }
+
+        /// Turn loyalty points into a discount on the caller's next order,
+        /// returning the discount granted
+        #[ink(message)]
+        pub fn redeem_points(&mut self, amount: u128) -> Result<Balance> {
+            let caller = self.env().caller();
+
+            // can't spend more points than held
+            let points = self.points_of(caller);
+            if amount > points {
+                return Err(BurgerShopError::InsufficientPoints);
+            }
+
+            // points are spent in whole discount units
+            let discount = amount / POINTS_PER_DISCOUNT_UNIT;
+            let spent = discount * POINTS_PER_DISCOUNT_UNIT;
+            self.loyalty_points.insert(caller, &(points - spent));
+
+            let pending = self
+                .loyalty_discounts
+                .get(caller)
+                .unwrap_or(0)
+                .checked_add(discount)
+                .ok_or(BurgerShopError::Overflow)?;
+            self.loyalty_discounts.insert(caller, &pending);
+
+            Ok(discount)
+        }
+
+        /// Reserve an order whose bill is split between several payers, each owing
+        /// the given share of the on-chain amount due. The shares must add up to
+        /// the full amount, and the order is paid once every payer has contributed.
+        #[ink(message)]
+        pub fn take_order_split_payment(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+            payers: Vec<(AccountId, Balance)>,
+        ) -> Result<Order> {
+            let (mut order, remaining_stock) =
+                self.prepare_order(list_of_items, OrderOptions::default())?;
+
+            // the shares must cover the bill exactly
+            let expected_val = self.chain_price(order.amount_due()?)?;
+            let shares = payers
+                .iter()
+                .try_fold(0, |total: Balance, (_, share)| total.checked_add(*share))
+                .ok_or(BurgerShopError::Overflow)?;
+            if shares != expected_val {
+                return Err(BurgerShopError::IncorrectPayment {
+                    expected: expected_val,
+                    received: shares,
+                });
+            }
+            order.status = OrderStatus::Reserved;
+            order.reservation_expiry = order.created_at.saturating_add(self.reservation_ttl);
+
+            // record what each payer owes
+            let mut accounts = Vec::new();
+            for (payer, share) in payers {
+                let key = (order.order_id, payer);
+                let owed = self.split_shares.get(key).unwrap_or(0) + share;
+                self.split_shares.insert(key, &owed);
+                if !accounts.contains(&payer) {
+                    accounts.push(payer);
+                }
+            }
+            self.split_payers.insert(order.order_id, &accounts);
+
+            // push to storage
+            self.insert_order(&order, remaining_stock);
+
+            Ok(order)
+        }
+
+        /// Pay the caller's share of a split order
+        #[ink(message, payable)]
+        pub fn contribute(&mut self, order_id: u32) -> Result<Order> {
+            let caller = self.env().caller();
+
+            // get the split order
+            let mut order = self
+                .orders_mapping
+                .get(order_id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if order.paid {
+                return Err(BurgerShopError::AlreadyPaid);
+            }
+
+            // only listed payers can contribute, up to their share
+            let key = (order_id, caller);
+            let share = self
+                .split_shares
+                .get(key)
+                .ok_or(BurgerShopError::Unauthorized)?;
+            let transferred_val = self.env().transferred_value();
+            let contributed = self
+                .contributions
+                .get(key)
+                .unwrap_or(0)
+                .checked_add(transferred_val)
+                .ok_or(BurgerShopError::Overflow)?;
+            if contributed > share {
+                return Err(BurgerShopError::Overpaid);
+            }
+            self.contributions.insert(key, &contributed);
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(caller),
+                to: Some(self.env().account_id()),
+                value: transferred_val,
+            });
+
+            order.paid_amount = order
+                .paid_amount
+                .checked_add(transferred_val)
+                .ok_or(BurgerShopError::Overflow)?;
+            if order.paid_amount == self.chain_price(order.amount_due()?)? {
+                order.paid = true;
+                order.payer = caller;
+                self.set_status(&mut order, OrderStatus::Paid);
+
+                // hold the payment in escrow
+                self.settle(&mut order)?;
+            }
+
+            // update storage
+            self.orders_mapping.insert(order_id, &order);
+
+            Ok(order)
+        }
+
+        /// Remove every reservation past its expiry and return its items to stock,
+        /// returning how many were removed. Payments made towards expired
+        /// reservations are refunded to whoever made them.
+        #[ink(message)]
+        pub fn expire_reservations(&mut self) -> Result<u32> {
+            let now = self.env().block_timestamp();
+            let reserved = self
+                .status_orders
+                .get(OrderStatus::Reserved)
+                .unwrap_or_default();
+
+            let mut removed = 0;
+            for (_, order) in self.collect_orders(reserved.iter()) {
+                if now > order.reservation_expiry {
+                    self.refund_payers(&order, order.customer, order.paid_amount)?;
+                    self.restore_stock(order.outlet, &order.list_of_items);
+                    self.remove_order(&order);
+                    removed += 1;
+                }
+            }
+            Ok(removed)
+        }
+
+        /// Remove every delivered order handed over before `older_than` to reclaim
+        /// storage, returning how many were removed. Revenue totals and the average
+        /// order value are kept, and orders with payment still in escrow are left
+        /// alone.
+        #[ink(message)]
+        pub fn purge_delivered(&mut self, older_than: Timestamp) -> Result<u32> {
+            self.ensure_owner()?;
+
+            let delivered = self
+                .status_orders
+                .get(OrderStatus::Delivered)
+                .unwrap_or_default();
+
+            let mut removed = 0;
+            for (id, order) in self.collect_orders(delivered.iter()) {
+                if order.escrow == 0
+                    && order
+                        .delivered_at
+                        .is_some_and(|delivered_at| delivered_at < older_than)
+                {
+                    self.order_item_revenue.remove(id);
+                    self.remove_order(&order);
+                    removed += 1;
+                }
+            }
+            Ok(removed)
+        }
+
+        /// Cancel an order and refund the customer
+        #[ink(message)]
+        pub fn cancel_order(&mut self, id: u32) -> Result<()> {
+            self.cancel(id, None)
+        }
+
+        /// Cancel an order and send its refund to another account
+        #[ink(message)]
+        pub fn cancel_order_to(&mut self, id: u32, refund_to: AccountId) -> Result<()> {
+            Self::ensure_not_zero(refund_to)?;
+            self.cancel(id, Some(refund_to))
+        }
+
+        /// Move an order to the next stage of its lifecycle
+        #[ink(message)]
+        pub fn advance_order_status(&mut self, id: u32) -> Result<OrderStatus> {
+            self.ensure_role(&[Role::Cook, Role::Manager])?;
+            self.advance(id)
+        }
+
+        /// Move several orders to the next stage of their lifecycle, returning the
+        /// outcome for each id. Orders that can't advance are skipped with the
+        /// reason instead of failing the whole batch.
+        #[ink(message)]
+        pub fn advance_orders_batch(
+            &mut self,
+            ids: Vec<u32>,
+        ) -> Result<Vec<(u32, Result<OrderStatus>)>> {
+            self.ensure_role(&[Role::Cook, Role::Manager])?;
+            Ok(ids.into_iter().map(|id| (id, self.advance(id))).collect())
+        }
+
+        /// Hand a ready order over to the customer
+        #[ink(message)]
+        pub fn fulfill_order(&mut self, id: u32) -> Result<()> {
+            self.ensure_role(&[Role::Cashier, Role::Manager])?;
+
+            // get the order to fulfill
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+
+            // only paid orders that are ready can be delivered
+            if !order.paid || order.status != OrderStatus::Ready {
+                return Err(BurgerShopError::OrderNotCompleted);
+            }
+            self.set_status(&mut order, OrderStatus::Delivered);
+
+            // update storage
+            self.orders_mapping.insert(id, &order);
+
+            Ok(())
+        }
+
+        /// Confirm an order was delivered, releasing its escrowed payment to the shop
+        #[ink(message)]
+        pub fn confirm_delivery(&mut self, id: u32) -> Result<()> {
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if self.env().caller() != order.customer {
+                return Err(BurgerShopError::NotOwnerOfOrder);
+            }
+            if order.disputed {
+                return Err(BurgerShopError::OrderDisputed);
+            }
+
+            self.release_escrow(&mut order)?;
+
+            // update storage
+            self.orders_mapping.insert(id, &order);
+
+            Ok(())
+        }
+
+        /// Dispute an order, freezing its escrowed payment until staff resolve it
+        #[ink(message)]
+        pub fn open_dispute(&mut self, id: u32) -> Result<()> {
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if self.env().caller() != order.customer {
+                return Err(BurgerShopError::NotOwnerOfOrder);
+            }
+            if order.escrow == 0 {
+                return Err(BurgerShopError::NotEscrowed);
+            }
+            if order.disputed {
+                return Err(BurgerShopError::OrderDisputed);
+            }
+            order.disputed = true;
+
+            // update storage
+            self.orders_mapping.insert(id, &order);
+
+            Ok(())
+        }
+
+        /// Settle a disputed order, either releasing its escrow to the shop or
+        /// refunding it to the customer and removing the order
+        #[ink(message)]
+        pub fn resolve_dispute(&mut self, id: u32, release: bool) -> Result<()> {
+            self.ensure_role(&[Role::Manager])?;
+
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+            if !order.disputed {
+                return Err(BurgerShopError::InvalidStatusTransition);
+            }
+            order.disputed = false;
+
+            if release {
+                self.release_escrow(&mut order)?;
+                self.orders_mapping.insert(id, &order);
+                return Ok(());
+            }
+
+            // refund the escrowed payment
+            let refund = order.escrow;
+            if refund > 0 {
+                self.refund(&order, order.customer, refund)?;
+
+                // Emit event
+                self.env().emit_event(Transfer {
+                    from: Some(self.env().account_id()),
+                    to: Some(order.customer),
+                    value: refund,
+                });
+                self.env().emit_event(Refunded {
+                    order_id: id,
+                    customer: order.customer,
+                    amount: refund,
+                });
+            }
+            self.drop_escrow(&mut order, refund);
+
+            // remove from storage
+            self.remove_order(&order);
+
+            Ok(())
+        }
+
+        /// Get the payments currently held in escrow
+        #[ink(message)]
+        pub fn escrowed_balance(&self) -> Balance {
+            self.escrowed
+        }
+
+        /// Give a staff role to an account
+        #[ink(message)]
+        pub fn grant_role(&mut self, account: AccountId, role: Role) -> Result<()> {
+            self.ensure_owner()?;
+            self.roles.insert(account, &role);
+            Ok(())
+        }
+
+        /// Take away an account's staff role
+        #[ink(message)]
+        pub fn revoke_role(&mut self, account: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.roles.remove(account);
+            Ok(())
+        }
+
+        /// Hand the shop over to a new owner
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+
+            // a zero owner would brick the shop
+            Self::ensure_not_zero(new_owner)?;
+
+            let previous = self.owner;
+            self.owner = new_owner;
+
+            // Emit event
+            self.env().emit_event(OwnershipTransferred {
+                previous,
+                new: new_owner,
+            });
+
+            Ok(())
+        }
+
+        /// Stop taking new orders
+        #[ink(message)]
+        pub fn pause(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.paused = true;
+            Ok(())
+        }
+
+        /// Start taking new orders again
+        #[ink(message)]
+        pub fn unpause(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+            self.paused = false;
+            Ok(())
+        }
+
+        /// Replace the contract code, keeping the existing storage
+        #[ink(message)]
+        pub fn set_code(&mut self, code_hash: ink::primitives::Hash) -> Result<()> {
+            self.ensure_owner()?;
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|_| BurgerShopError::UpgradeFailed)
+        }
+
+        /// Turn refunds on or off, e.g. for final-sale promotions
+        #[ink(message)]
+        pub fn set_refunds_enabled(&mut self, enabled: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.refunds_enabled = enabled;
+            Ok(())
+        }
+
+        /// Turn the customer allowlist on or off
+        #[ink(message)]
+        pub fn set_allowlist_enabled(&mut self, enabled: bool) -> Result<()> {
+            self.ensure_owner()?;
+            self.allowlist_enabled = enabled;
+            Ok(())
+        }
+
+        /// Add an account to the customer allowlist
+        #[ink(message)]
+        pub fn allow(&mut self, account: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.allowlist.insert(account, &());
+            Ok(())
+        }
+
+        /// Remove an account from the customer allowlist
+        #[ink(message)]
+        pub fn disallow(&mut self, account: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.allowlist.remove(account);
+            Ok(())
+        }
+
+        /// Ban an account from placing orders
+        #[ink(message)]
+        pub fn ban(&mut self, account: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.blacklist.insert(account, &());
+            Ok(())
+        }
+
+        /// Lift a ban so an account can place orders again
+        #[ink(message)]
+        pub fn unban(&mut self, account: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            self.blacklist.remove(account);
+            Ok(())
+        }
+
+        /// Remove every order from storage, keeping the menu and prices. Order ids,
+        /// including those per outlet, keep counting up and are never reused.
+        #[ink(message)]
+        pub fn clear_all_orders(&mut self) -> Result<()> {
+            self.ensure_owner()?;
+
+            for id in core::mem::take(&mut self.order_ids) {
+                if let Some(mut order) = self.orders_mapping.take(id) {
+                    // hand back whatever the shop hasn't been released yet:
+                    // escrowed payments and part-paid reservations
+                    let held = if order.paid {
+                        order.escrow
+                    } else {
+                        order.paid_amount
+                    };
+                    self.refund_payers(&order, order.customer, held)?;
+                    let escrow = order.escrow;
+                    self.drop_escrow(&mut order, escrow);
+
+                    self.customer_orders.remove(order.customer);
+                    self.outlet_orders
+                        .remove((order.outlet, order.outlet_order_id));
+                    for payer in self.split_payers.take(id).unwrap_or_default() {
+                        self.split_shares.remove((id, payer));
+                        self.contributions.remove((id, payer));
+                    }
+
+                    // Emit event
+                    self.env().emit_event(OrderRemoved {
+                        order_id: id,
+                        customer: order.customer,
+                    });
+                }
+            }
+            for status in OrderStatus::variants() {
+                self.status_orders.remove(status);
+                self.status_counts.remove(status);
+            }
+            self.order_count = 0;
+
+            Ok(())
+        }
+
+        /// Set the price of a burger variant
+        #[ink(message)]
+        pub fn set_price(&mut self, menu: BurgerMenu, price: Balance) -> Result<()> {
+            self.ensure_owner()?;
+
+            match menu {
+                // specials keep their price in the menu registry
+                BurgerMenu::Special(id) => {
+                    let mut entry = self
+                        .menu_items
+                        .get(id)
+                        .ok_or(BurgerShopError::UnknownMenuItem)?;
+                    entry.price = price;
+                    self.menu_items.insert(id, &entry);
+                }
+                // update price list
+                _ => {
+                    self.prices.insert(&menu, &price);
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Set the maximum number of burgers allowed in a single order
+        #[ink(message)]
+        pub fn set_max_items_per_order(&mut self, max_items_per_order: u32) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_items_per_order = max_items_per_order;
+            Ok(())
+        }
+
+        /// Set the maximum quantity of a single burger allowed in one order item
+        #[ink(message)]
+        pub fn set_max_quantity_per_item(&mut self, max_quantity_per_item: u32) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_quantity_per_item = max_quantity_per_item;
+            Ok(())
+        }
+
+        /// Set the maximum number of orders a customer may place per day
+        #[ink(message)]
+        pub fn set_max_orders_per_day(&mut self, max_orders_per_day: u32) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_orders_per_day = max_orders_per_day;
+            Ok(())
+        }
+
+        /// Set how many loyalty points are earned per unit spent
+        #[ink(message)]
+        pub fn set_points_per_unit(&mut self, points_per_unit: u128) -> Result<()> {
+            self.ensure_owner()?;
+            self.points_per_unit = points_per_unit;
+            Ok(())
+        }
+
+        /// Set the sales tax rate in basis points, e.g. 825 for 8.25%
+        #[ink(message)]
+        pub fn set_tax_basis_points(&mut self, tax_basis_points: u16) -> Result<()> {
+            self.ensure_owner()?;
+            self.tax_basis_points = tax_basis_points;
+            Ok(())
+        }
+
+        /// Set the smallest item total an order may have
+        #[ink(message)]
+        pub fn set_min_order_value(&mut self, min_order_value: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.min_order_value = min_order_value;
+            Ok(())
+        }
+
+        /// Set a daily happy hour window, in seconds of the day (UTC), with a
+        /// percentage off every order placed inside it. The window may wrap past
+        /// midnight, and a percentage of zero turns happy hour off.
+        #[ink(message)]
+        pub fn set_happy_hour(&mut self, start: u32, end: u32, percent: u8) -> Result<()> {
+            self.ensure_owner()?;
+
+            if start >= SECONDS_PER_DAY || end >= SECONDS_PER_DAY || percent > 100 {
+                return Err(BurgerShopError::InvalidHappyHour);
+            }
+            self.happy_hour_start = start;
+            self.happy_hour_end = end;
+            self.happy_hour_percent = percent;
+
+            Ok(())
+        }
+
+        /// Set the surge pricing rate, in basis points added per order not yet
+        /// delivered, and the most the surge may add in total
+        #[ink(message)]
+        pub fn set_surge_pricing(
+            &mut self,
+            surge_basis_points_per_pending: u16,
+            max_surge_basis_points: u32,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            self.surge_basis_points_per_pending = surge_basis_points_per_pending;
+            self.max_surge_basis_points = max_surge_basis_points;
+            Ok(())
+        }
+
+        /// Set the refundable deposit charged per order for reusable packaging
+        #[ink(message)]
+        pub fn set_container_deposit(&mut self, container_deposit: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.container_deposit = container_deposit;
+            Ok(())
+        }
+
+        /// Set the account receiving a cut of every order and its size in basis points
+        #[ink(message)]
+        pub fn set_service_fee(
+            &mut self,
+            fee_recipient: AccountId,
+            fee_basis_points: u16,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            Self::ensure_not_zero(fee_recipient)?;
+
+            if Balance::from(fee_basis_points) > BASIS_POINTS {
+                return Err(BurgerShopError::InvalidFee);
+            }
+            self.fee_recipient = fee_recipient;
+            self.fee_basis_points = fee_basis_points;
+
+            Ok(())
+        }
+
+        /// Set the most that can be refunded within a single block
+        #[ink(message)]
+        pub fn set_max_refund_per_block(&mut self, max_refund_per_block: Balance) -> Result<()> {
+            self.ensure_owner()?;
+            self.max_refund_per_block = max_refund_per_block;
+            Ok(())
+        }
+
+        /// Set how fractional discount and tax amounts are rounded
+        #[ink(message)]
+        pub fn set_rounding_mode(&mut self, rounding_mode: RoundingMode) -> Result<()> {
+            self.ensure_owner()?;
+            self.rounding_mode = rounding_mode;
+            Ok(())
+        }
+
+        /// Set how long in milliseconds after ordering a customer may cancel for free
+        #[ink(message)]
+        pub fn set_cancel_grace_period(&mut self, cancel_grace_period: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            self.cancel_grace_period = cancel_grace_period;
+            Ok(())
+        }
+
+        /// Set the share of an order, in basis points, kept by the shop when it's
+        /// cancelled after the kitchen has started and the grace period has passed
+        #[ink(message)]
+        pub fn set_cancellation_fee(&mut self, cancellation_fee_basis_points: u16) -> Result<()> {
+            self.ensure_owner()?;
+
+            if Balance::from(cancellation_fee_basis_points) > BASIS_POINTS {
+                return Err(BurgerShopError::InvalidFee);
+            }
+            self.cancellation_fee_basis_points = cancellation_fee_basis_points;
+
+            Ok(())
+        }
+
+        /// Set how long in milliseconds reservations are held before expiring
+        #[ink(message)]
+        pub fn set_reservation_ttl(&mut self, reservation_ttl: Timestamp) -> Result<()> {
+            self.ensure_owner()?;
+            self.reservation_ttl = reservation_ttl;
+            Ok(())
+        }
+
+        /// Add to the available stock of a burger at an outlet. Stock is only
+        /// tracked for a burger once it has been restocked.
+        #[ink(message)]
+        pub fn restock(&mut self, outlet: u32, menu: BurgerMenu, amount: u32) -> Result<()> {
+            self.ensure_role(&[Role::Cashier, Role::Cook, Role::Manager])?;
+            self.ensure_outlet(outlet)?;
+
+            let key = (outlet, menu);
+            let level = self
+                .stock
+                .get(&key)
+                .unwrap_or(0)
+                .checked_add(amount)
+                .ok_or(BurgerShopError::Overflow)?;
+            self.stock.insert(&key, &level);
+
+            Ok(())
+        }
+
+        /// Add to the available stock of several burgers at an outlet at once
+        #[ink(message)]
+        pub fn restock_batch(&mut self, outlet: u32, items: Vec<(BurgerMenu, u32)>) -> Result<()> {
+            self.ensure_role(&[Role::Cashier, Role::Cook, Role::Manager])?;
+            self.ensure_outlet(outlet)?;
+
+            for (menu, amount) in items {
+                let key = (outlet, menu);
+                let level = self
+                    .stock
+                    .get(&key)
+                    .unwrap_or(0)
+                    .checked_add(amount)
+                    .ok_or(BurgerShopError::Overflow)?;
+                self.stock.insert(&key, &level);
+            }
+
+            Ok(())
+        }
+
+        /// Set the stock level at an outlet at or below which an order raises a low
+        /// stock alert
+        #[ink(message)]
+        pub fn set_reorder_point(
+            &mut self,
+            outlet: u32,
+            menu: BurgerMenu,
+            point: u32,
+        ) -> Result<()> {
+            self.ensure_owner()?;
+            self.ensure_outlet(outlet)?;
+            self.reorder_points.insert((outlet, menu), &point);
+            Ok(())
+        }
+
+        /// Mark a burger as available or unavailable to order, independent of stock
+        #[ink(message)]
+        pub fn set_available(&mut self, menu: BurgerMenu, available: bool) -> Result<()> {
+            self.ensure_role(&[Role::Cashier, Role::Cook, Role::Manager])?;
+            self.availability.insert(&menu, &available);
+            Ok(())
+        }
+
+        /// Create a coupon code worth a percentage off an order
+        #[ink(message)]
+        pub fn create_coupon(&mut self, code: Vec<u8>, percent: u8) -> Result<()> {
+            self.ensure_owner()?;
+
+            // can't discount more than the full price
+            if percent > 100 {
+                return Err(BurgerShopError::InvalidCoupon);
+            }
+            self.coupons.insert(&code, &percent);
+
+            Ok(())
+        }
+
+        /// Revoke a coupon code so it can no longer be redeemed
+        #[ink(message)]
+        pub fn revoke_coupon(&mut self, code: Vec<u8>) -> Result<()> {
+            self.ensure_owner()?;
+
+            if !self.coupons.contains(&code) {
+                return Err(BurgerShopError::InvalidCoupon);
+            }
+            self.coupons.remove(&code);
+
+            Ok(())
+        }
+
+        /// Add a new burger to the menu, returning its special menu id
+        #[ink(message)]
+        pub fn add_menu_item(&mut self, name: Vec<u8>, price: Balance) -> Result<u32> {
+            self.ensure_owner()?;
+
+            // register the new item
+            let name = BoundedName::from_slice(&name)?;
+            let id = self.next_menu_item_id;
+            self.menu_items.insert(
+                id,
+                &MenuEntry {
+                    name,
+                    price,
+                    available: true,
+                },
+            );
+            self.next_menu_item_id += 1;
+
+            Ok(id)
+        }
+
+        /// Take a special burger off the menu
+        #[ink(message)]
+        pub fn disable_menu_item(&mut self, id: u32) -> Result<()> {
+            self.ensure_owner()?;
+
+            let mut entry = self
+                .menu_items
+                .get(id)
+                .ok_or(BurgerShopError::UnknownMenuItem)?;
+            entry.available = false;
+            self.menu_items.insert(id, &entry);
+
+            Ok(())
+        }
+
+        /// Add a combo meal bundling items at a fixed price, returning its id
+        #[ink(message)]
+        pub fn add_combo(&mut self, items: Vec<FoodItem>, price: Balance) -> Result<u32> {
+            self.ensure_owner()?;
+            self.validate_items(&items)?;
+
+            // register the new combo
+            let id = self.next_combo_id;
+            self.combos.insert(id, &Combo { items, price });
+            self.next_combo_id += 1;
+
+            Ok(id)
+        }
+
+        /// Take a combo meal off the menu
+        #[ink(message)]
+        pub fn remove_combo(&mut self, combo_id: u32) -> Result<()> {
+            self.ensure_owner()?;
+
+            if self.combos.take(combo_id).is_none() {
+                return Err(BurgerShopError::UnknownCombo);
+            }
+
+            Ok(())
+        }
+
+        /// Register a new outlet with its own stock and revenue, returning its id
+        #[ink(message)]
+        pub fn add_outlet(&mut self, name: Vec<u8>) -> Result<u32> {
+            self.ensure_owner()?;
+
+            // register the new outlet
+            let name = BoundedName::from_slice(&name)?;
+            let id = self.next_outlet_id;
+            self.outlets.insert(id, &name);
+            self.next_outlet_id = id.checked_add(1).ok_or(BurgerShopError::Overflow)?;
+
+            Ok(id)
+        }
+
+        /// Withdraw shop revenue to the owner
+        #[ink(message)]
+        pub fn withdraw(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_owner()?;
+
+            // make sure the shop can cover the amount
+            let balance = self.env().balance();
+            if amount > balance {
+                return Err(BurgerShopError::InsufficientBalance);
+            }
+
+            // escrowed payments aren't the shop's to withdraw yet
+            if amount > balance - self.escrowed.min(balance) {
+                return Err(BurgerShopError::StillEscrowed);
+            }
+
+            // send funds to owner
+            if self.env().transfer(self.owner, amount).is_err() {
+                return Err(BurgerShopError::PaymentError);
+            }
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(self.env().account_id()),
+                to: Some(self.owner),
+                value: amount,
+            });
+
+            Ok(())
+        }
+
+        /// Sweep the shop's balance to a rescue account while paused, leaving
+        /// payments still held in escrow behind
+        #[ink(message)]
+        pub fn emergency_withdraw(&mut self, to: AccountId) -> Result<()> {
+            self.ensure_owner()?;
+            Self::ensure_not_zero(to)?;
+
+            // only a paused shop can be drained
+            if !self.paused {
+                return Err(BurgerShopError::NotPaused);
+            }
+
+            // send everything the shop owns to the rescue account
+            let amount = self.env().balance().saturating_sub(self.escrowed);
+            if self.env().transfer(to, amount).is_err() {
+                return Err(BurgerShopError::PaymentError);
+            }
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(self.env().account_id()),
+                to: Some(to),
+                value: amount,
+            });
+
+            Ok(())
+        }
+
+        /// Get every burger currently on the menu with its price
+        #[ink(message)]
+        pub fn get_menu(&self) -> Vec<(BurgerMenu, Balance)> {
+            let specials = (0..self.next_menu_item_id).map(BurgerMenu::Special);
+
+            BurgerMenu::variants()
+                .into_iter()
+                .chain(specials)
+                .filter_map(|menu| self.price_of(&menu).ok().map(|price| (menu, price)))
+                .collect()
+        }
+
+        /// Get every burger on the menu whose tracked stock at an outlet is at or
+        /// below `threshold`
+        #[ink(message)]
+        pub fn low_stock(&self, outlet: u32, threshold: u32) -> Result<Vec<(BurgerMenu, u32)>> {
+            self.ensure_outlet(outlet)?;
+
+            Ok(self
+                .get_menu()
+                .into_iter()
+                .filter_map(|(menu, _)| {
+                    let level = self.stock.get((outlet, menu.clone()))?;
+                    Some((menu, level))
+                })
+                .filter(|(_, level)| *level <= threshold)
+                .collect())
+        }
+
+        /// Get the stock of a burger at an outlet, or `None` if it isn't tracked
+        #[ink(message)]
+        pub fn stock_of(&self, outlet: u32, menu: BurgerMenu) -> Result<Option<u32>> {
+            self.ensure_outlet(outlet)?;
+            Ok(self.stock.get((outlet, menu)))
+        }
+
+        /// Estimate the on-chain value the caller would pay for the given items right now
+        #[ink(message)]
+        pub fn estimate_order_price(&self, list_of_items: Vec<FoodItem>) -> Result<Balance> {
+            Self::ensure_no_zero_amounts(&list_of_items)?;
+            let loyalty_discount = self.loyalty_discounts.get(self.env().caller()).unwrap_or(0);
+            let (total_price, _) = self.price_items(
+                Order::total_price(&list_of_items, self)?,
+                0,
+                loyalty_discount,
+                self.pending_orders(),
+            )?;
+            let tax = self.tax_on(total_price)?;
+            self.chain_price(
+                total_price
+                    .checked_add(tax)
+                    .and_then(|due| due.checked_add(self.container_deposit))
+                    .ok_or(BurgerShopError::Overflow)?,
+            )
+        }
+
+        /// Convert a price in menu units into the on-chain value to pay
+        #[ink(message)]
+        pub fn to_chain_value(&self, price_units: Balance) -> Result<Balance> {
+            self.chain_price(price_units)
+        }
+
+        /// Convert an on-chain value back into menu price units, rounding down
+        #[ink(message)]
+        #[allow(clippy::wrong_self_convention)]
+        pub fn from_chain_value(&self, chain_value: Balance) -> Balance {
+            chain_value / self.price_multiplier
+        }
+
+        /// Get the version of the contract logic currently live
+        #[ink(message)]
+        pub fn version(&self) -> u32 {
+            VERSION
+        }
+
+        /// Get the shop's tunable parameters in a single call
+        #[ink(message)]
+        pub fn get_config(&self) -> ShopConfig {
+            ShopConfig {
+                price_multiplier: self.price_multiplier,
+                tax_basis_points: self.tax_basis_points,
+                min_order_value: self.min_order_value,
+                max_items_per_order: self.max_items_per_order,
+                paused: self.paused,
+                owner: self.owner,
+            }
+        }
+
+        /// Get the contract's current on-chain balance. Unrestricted, since the
+        /// balance of any account is public on-chain anyway.
+        #[ink(message)]
+        pub fn shop_balance(&self) -> Balance {
+            self.env().balance()
+        }
+
+        /// Get a single order from storage
+        #[ink(message)]
+        pub fn get_single_order(&self, id: u32) -> Result<Order> {
+            // get single order
+            let order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+
+            // emit event
+            self.env().emit_event(GetSingleOrder {
+                single_order: order.clone(),
+            });
+
+            // return order
+            Ok(order)
+        }
+
+        /// Get an order by its id within an outlet
+        #[ink(message)]
+        pub fn get_outlet_order(&self, outlet: u32, outlet_order_id: u32) -> Result<Order> {
+            self.ensure_outlet(outlet)?;
+            self.outlet_orders
+                .get((outlet, outlet_order_id))
+                .and_then(|id| self.orders_mapping.get(id))
+                .ok_or(BurgerShopError::OrderNotFound)
+        }
+
+        /// Get just the status of an order, for cheap progress polling
+        #[ink(message)]
+        pub fn get_order_status(&self, id: u32) -> Result<OrderStatus> {
+            self.orders_mapping
+                .get(id)
+                .map(|order| order.status)
+                .ok_or(BurgerShopError::OrderNotFound)
+        }
+
+        /// Get the account that made the latest payment towards an order, which
+        /// may differ from its customer
+        #[ink(message)]
+        pub fn get_order_payer(&self, id: u32) -> Result<AccountId> {
+            self.orders_mapping
+                .get(id)
+                .map(|order| order.payer)
+                .ok_or(BurgerShopError::OrderNotFound)
+        }
+
+        /// Get the number of distinct items in an order and their total quantity
+        #[ink(message)]
+        pub fn order_item_count(&self, id: u32) -> Result<(u32, u32)> {
+            let order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+
+            let quantity = order
+                .list_of_items
+                .iter()
+                .fold(0u32, |count, item| count.saturating_add(item.amount));
+            Ok((order.list_of_items.len() as u32, quantity))
+        }
+
+        /// Get the SCALE encoding of an order, for clients decoding it off-chain
+        #[ink(message)]
+        pub fn encode_order(&self, id: u32) -> Result<Vec<u8>> {
+            self.orders_mapping
+                .get(id)
+                .map(|order| order.encode())
+                .ok_or(BurgerShopError::OrderNotFound)
+        }
+
+        /// Get the Blake2x256 hash of an order's SCALE encoding, so clients can check
+        /// an off-chain receipt against on-chain state
+        #[ink(message)]
+        pub fn order_hash(&self, id: u32) -> Result<[u8; 32]> {
+            self.orders_mapping
+                .get(id)
+                .map(|order| self.env().hash_encoded::<Blake2x256, _>(&order))
+                .ok_or(BurgerShopError::OrderNotFound)
+        }
+
+        /// Get an itemized receipt for an order, with each line's share of the
+        /// order's total price
+        #[ink(message)]
+        pub fn get_order_receipt(&self, id: u32) -> Result<Vec<(BurgerMenu, u32, Balance)>> {
+            let order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+
+            Ok(order
+                .list_of_items
+                .iter()
+                .zip(order.line_totals()?)
+                .map(|(item, total)| (item.burger_menu.clone(), item.amount, total))
+                .collect())
+        }
+
+        /// Get the orders in the storage
+        #[ink(message)]
+        pub fn get_orders(&self) -> Result<Option<Vec<(u32, Order)>>> {
+            self.ensure_owner()?;
+
+            if !self.order_ids.is_empty() {
+                // Get all orders
+                let myorders: Vec<(u32, Order)> = self.collect_orders(self.order_ids.iter());
+
+                // Emit events
+                self.env().emit_event(GetAllOrders {
+                    orders: myorders.clone(),
+                });
+
+                // converts reference to an owned/new vector
+                Ok(Some(myorders))
+            } else {
+                Ok(None)
+            }
+        }
+
+        /// Get the number of orders in storage
+        #[ink(message)]
+        pub fn order_count(&self) -> u32 {
+            self.order_count
+        }
+
+        /// Get a page of at most `limit` orders beginning at `start`
+        #[ink(message)]
+        pub fn get_orders_paged(&self, start: u32, limit: u32) -> Vec<(u32, Order)> {
+            let limit = limit.min(MAX_PAGE_SIZE);
+
+            self.collect_orders(
+                self.order_ids
+                    .iter()
+                    .skip(start as usize)
+                    .take(limit as usize),
+            )
+        }
+
+        /// Get at most `limit` orders with the highest totals, largest first. The
+        /// limit is capped at `MAX_TOP_ORDERS` to bound the cost of the query.
+        #[ink(message)]
+        pub fn get_top_orders(&self, limit: u32) -> Vec<(u32, Order)> {
+            let limit = limit.min(MAX_TOP_ORDERS) as usize;
+            let by_price_desc =
+                |a: &(u32, Order), b: &(u32, Order)| b.1.total_price.cmp(&a.1.total_price);
+
+            // only the top orders need sorting
+            let mut orders = self.collect_orders(self.order_ids.iter());
+            if limit < orders.len() {
+                orders.select_nth_unstable_by(limit, by_price_desc);
+                orders.truncate(limit);
+            }
+            orders.sort_unstable_by(by_price_desc);
+
+            orders
+        }
+
+        /// Get the orders placed by a customer
+        #[ink(message)]
+        pub fn get_orders_by_customer(&self, customer: AccountId) -> Vec<(u32, Order)> {
+            self.collect_orders(
+                self.customer_orders
+                    .get(customer)
+                    .unwrap_or_default()
+                    .iter(),
+            )
+        }
+
+        /// Get the orders placed by the caller
+        #[ink(message)]
+        pub fn get_my_orders(&self) -> Vec<(u32, Order)> {
+            self.get_orders_by_customer(self.env().caller())
+        }
+
+        /// Get the number of orders placed by a customer
+        #[ink(message)]
+        pub fn customer_order_count(&self, who: AccountId) -> u32 {
+            self.customer_orders.get(who).unwrap_or_default().len() as u32
+        }
+
+        /// Get the orders created within the inclusive time range `[from, to]`
+        #[ink(message)]
+        pub fn get_orders_between(
+            &self,
+            from: Timestamp,
+            to: Timestamp,
+        ) -> Result<Vec<(u32, Order)>> {
+            if from > to {
+                return Err(BurgerShopError::InvalidRange);
+            }
+
+            Ok(self
+                .collect_orders(self.order_ids.iter())
+                .into_iter()
+                .filter(|(_, order)| (from..=to).contains(&order.created_at))
+                .collect())
+        }
+
+        /// Get the orders created at or after the given block, for indexers
+        /// catching up from a checkpoint
+        #[ink(message)]
+        pub fn get_orders_since_block(&self, block: BlockNumber) -> Vec<(u32, Order)> {
+            self.collect_orders(self.order_ids.iter())
+                .into_iter()
+                .filter(|(_, order)| order.created_at_block >= block)
+                .collect()
+        }
+
+        /// Get the pre-orders whose pickup time has passed but aren't delivered yet
+        #[ink(message)]
+        pub fn due_preorders(&self) -> Vec<(u32, Order)> {
+            let now = self.env().block_timestamp();
+
+            self.collect_orders(self.order_ids.iter())
+                .into_iter()
+                .filter(|(_, order)| {
+                    order.status != OrderStatus::Delivered
+                        && order.ready_at.is_some_and(|ready_at| ready_at <= now)
+                })
+                .collect()
+        }
+
+        /// Get the cumulative revenue from all paid orders
+        #[ink(message)]
+        pub fn get_total_revenue(&self) -> Result<Balance> {
+            self.ensure_owner()?;
+            Ok(self.total_revenue)
+        }
+
+        /// Get the average revenue per recorded sale, or zero with no sales
+        #[ink(message)]
+        pub fn average_order_value(&self) -> Balance {
+            self.total_revenue
+                .checked_div(Balance::from(self.sales_count))
+                .unwrap_or(0)
+        }
+
+        /// Get the cumulative revenue from paid orders at an outlet
+        #[ink(message)]
+        pub fn get_outlet_revenue(&self, outlet: u32) -> Result<Balance> {
+            self.ensure_owner()?;
+            self.ensure_outlet(outlet)?;
+            Ok(self.outlet_revenue.get(outlet).unwrap_or(0))
+        }
+
+        /// Get the revenue attributed to each burger ever on the menu at an outlet
+        #[ink(message)]
+        pub fn revenue_by_item(&self, outlet: u32) -> Result<Vec<(BurgerMenu, Balance)>> {
+            self.ensure_outlet(outlet)?;
+            let specials = (0..self.next_menu_item_id).map(BurgerMenu::Special);
+
+            Ok(BurgerMenu::variants()
+                .into_iter()
+                .chain(specials)
+                .map(|menu| {
+                    let revenue = self.item_revenue.get((outlet, menu.clone())).unwrap_or(0);
+                    (menu, revenue)
+                })
+                .collect())
+        }
+
+        /// Build a new unpaid order for the caller and check it can be served,
+        /// returning it with the stock levels left after taking its items
+        fn prepare_order(
+            &self,
+            list_of_items: Vec<FoodItem>,
+            options: OrderOptions,
+        ) -> Result<(Order, Vec<(BurgerMenu, u32)>)> {
+            // No new orders while the shop is paused
+            if self.paused {
+                return Err(BurgerShopError::ShopPaused);
+            }
+
+            // Get the account the order is for, defaulting to the caller
+            let caller = options.customer.unwrap_or_else(|| Self::env().caller());
+
+            // Ensure the user is valid
+            if caller == self.env().account_id() {
+                return Err(BurgerShopError::CallerIsShop);
+            }
+
+            // Banned customers can't order, even if allowlisted
+            if self.blacklist.contains(caller) {
+                return Err(BurgerShopError::Banned);
+            }
+
+            // Only listed customers may order while the allowlist is on
+            if self.allowlist_enabled && !self.allowlist.contains(caller) {
+                return Err(BurgerShopError::NotAllowlisted);
+            }
+
+            // Ensure the customer is under today's order cap
+            let day = self.env().block_timestamp() / MILLIS_PER_DAY;
+            if self.daily_orders.get((caller, day)).unwrap_or(0) >= self.max_orders_per_day {
+                return Err(BurgerShopError::DailyLimitReached);
+            }
+
+            // Ensure the order is for a known outlet
+            self.ensure_outlet(options.outlet)?;
+
+            // Ensure the items can make up an order
+            let list_of_items = Self::merge_items(list_of_items)?;
+            self.validate_items(&list_of_items)?;
+
+            // Generate local id. Ids are never reused, so refuse new orders
+            // once they run out rather than wrapping around.
+            let id = self.next_order_id;
+            id.checked_add(1).ok_or(BurgerShopError::IdSpaceExhausted)?;
+
+            // Calculate and set order price
+            let total_price = match options.fixed_price {
+                Some(price) => price,
+                None => Order::total_price(&list_of_items, self)?,
+            };
+            let mut order = Order::new(
+                list_of_items,
+                caller,
+                id,
+                self.env().block_timestamp(),
+                self,
+            )?;
+            order.total_price = total_price;
+            order.created_at_block = self.env().block_number();
+            order.payer = Self::env().caller();
+            self.ensure_min_order_value(total_price)?;
+            order.outlet = options.outlet;
+            order.outlet_order_id = self.next_outlet_order_ids.get(options.outlet).unwrap_or(0);
+
+            // Attach the customer's note
+            if options.note.len() > MAX_NOTE_LENGTH {
+                return Err(BurgerShopError::NoteTooLong);
+            }
+            order.note = options.note;
+            order.tip = options.tip;
+            order.deposit = self.container_deposit;
+            order.ready_at = options.ready_at;
+
+            // Look up the coupon discount
+            if let Some(code) = options.coupon {
+                order.coupon_percent = self
+                    .coupons
+                    .get(&code)
+                    .ok_or(BurgerShopError::InvalidCoupon)?;
+            }
+
+            // Apply surge pricing, discounts and any redeemed loyalty points
+            let loyalty_discount = self.loyalty_discounts.get(caller).unwrap_or(0);
+            (order.total_price, order.loyalty_discount) = self.price_items(
+                order.total_price,
+                order.coupon_percent,
+                loyalty_discount,
+                self.pending_orders(),
+            )?;
+
+            // Add sales tax on the discounted price
+            order.tax = self.tax_on(order.total_price)?;
+
+            // Ensure that the order hasn't been paid for already
+            if order.paid {
+                return Err(BurgerShopError::AlreadyPaid);
+            }
+
+            // Ensure every item is in stock
+            let remaining_stock = self.remaining_stock(order.outlet, &order.list_of_items)?;
+
+            Ok((order, remaining_stock))
+        }
+
+        /// Check a list of items is acceptable for a single order
+        fn validate_items(&self, list_of_items: &[FoodItem]) -> Result<()> {
+            // Ensure the order contains at least 1 item
+            if list_of_items.is_empty() {
+                return Err(BurgerShopError::EmptyOrder);
+            }
+            Self::ensure_no_zero_amounts(list_of_items)?;
+
+            // Ensure the order isn't too large to serve
+            let item_count = list_of_items
+                .iter()
+                .fold(0u32, |count, item| count.saturating_add(item.amount));
+            if item_count > self.max_items_per_order {
+                return Err(BurgerShopError::OrderTooLarge);
+            }
+
+            // Ensure no single line asks for too many of one burger
+            if list_of_items
+                .iter()
+                .any(|item| item.amount > self.max_quantity_per_item)
+            {
+                return Err(BurgerShopError::QuantityTooHigh);
+            }
+
+            // Ensure the kitchen is serving every item
+            if list_of_items
+                .iter()
+                .any(|item| !self.availability.get(&item.burger_menu).unwrap_or(true))
+            {
+                return Err(BurgerShopError::ItemUnavailable);
+            }
+
+            Ok(())
+        }
+
+        /// Combine items for the same burger into a single item, keeping the order
+        /// in which each burger first appears
+        fn merge_items(list_of_items: Vec<FoodItem>) -> Result<Vec<FoodItem>> {
+            let mut merged: Vec<FoodItem> = Vec::new();
+            for item in list_of_items {
+                match merged
+                    .iter_mut()
+                    .find(|existing| existing.burger_menu == item.burger_menu)
+                {
+                    Some(existing) => {
+                        existing.amount = existing
+                            .amount
+                            .checked_add(item.amount)
+                            .ok_or(BurgerShopError::Overflow)?;
+                    }
+                    None => merged.push(item),
+                }
+            }
+            Ok(merged)
+        }
+
+        /// Take an order and its payment from the caller
+        fn place_order(
+            &mut self,
+            list_of_items: Vec<FoodItem>,
+            options: OrderOptions,
+        ) -> Result<Order> {
+            let caller = self.env().caller();
+            let transferred_val = self.env().transferred_value();
+
+            // a retried order hands back the original and returns the payment
+            let client_ref = options.client_ref.clone();
+            if let Some(client_ref) = &client_ref {
+                if let Some(order) = self
+                    .client_refs
+                    .get((caller, client_ref.clone()))
+                    .and_then(|id| self.orders_mapping.get(id))
+                {
+                    if transferred_val > 0 {
+                        if self.env().transfer(caller, transferred_val).is_err() {
+                            return Err(BurgerShopError::PaymentError);
+                        }
+
+                        // Emit event
+                        self.env().emit_event(Transfer {
+                            from: Some(self.env().account_id()),
+                            to: Some(caller),
+                            value: transferred_val,
+                        });
+                        self.env().emit_event(Refunded {
+                            order_id: order.order_id,
+                            customer: caller,
+                            amount: transferred_val,
+                        });
+                    }
+                    return Ok(order);
+                }
+            }
+
+            let (mut order, remaining_stock) = self.prepare_order(list_of_items, options)?;
+
+            let expected_val = self.chain_price(order.amount_due()?)?;
+
+            // Ensure the value sent covers total_price
+            if transferred_val < expected_val {
+                return Err(BurgerShopError::IncorrectPayment {
+                    expected: expected_val,
+                    received: transferred_val,
+                });
+            }
+
+            // print total price
+            debug_println!("Expected value: {}", order.total_price);
+
+            // print transferred_val
+            debug_println!(
+                "Expected received payment without conversion: {}",
+                transferred_val
+            );
+
+            // the payment arrives with the call, so record it
+            order.paid = true;
+            order.paid_amount = expected_val;
+
+            // hand back anything paid above the total, which already
+            // includes any coupon discount
+            let change = transferred_val - expected_val;
+            if change > 0 {
+                if self.env().transfer(order.customer, change).is_err() {
+                    return Err(BurgerShopError::PaymentError);
+                }
+
+                // Emit event
+                self.env().emit_event(Transfer {
+                    from: Some(self.env().account_id()),
+                    to: Some(order.customer),
+                    value: change,
+                });
+                self.env().emit_event(Refunded {
+                    order_id: order.order_id,
+                    customer: order.customer,
+                    amount: change,
+                });
+            }
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(order.customer),
+                to: Some(self.env().account_id()),
+                value: self.chain_price(order.total_price)?,
+            });
+
+            // hold the payment in escrow
+            self.settle(&mut order)?;
+
+            // push to storage
+            self.insert_order(&order, remaining_stock);
+            if let Some(client_ref) = client_ref {
+                self.client_refs
+                    .insert((caller, client_ref), &order.order_id);
+            }
+
+            Ok(order)
+        }
+
+        /// Get the cumulative tips from all paid orders
+        #[ink(message)]
+        pub fn get_total_tips(&self) -> Result<Balance> {
+            self.ensure_owner()?;
+            Ok(self.total_tips)
+        }
+
+        /// Get the cumulative sales tax from all paid orders
+        #[ink(message)]
+        pub fn get_total_tax_collected(&self) -> Result<Balance> {
+            self.ensure_owner()?;
+            Ok(self.total_tax)
+        }
+
+        /// Get the orders currently in the given status
+        #[ink(message)]
+        pub fn get_orders_by_status(&self, status: OrderStatus) -> Vec<(u32, Order)> {
+            self.collect_orders(self.status_orders.get(status).unwrap_or_default().iter())
+        }
+
+        /// Get the orders that are fully paid, or those still awaiting payment
+        #[ink(message)]
+        pub fn get_orders_by_paid(&self, paid: bool) -> Vec<(u32, Order)> {
+            self.collect_orders(self.order_ids.iter())
+                .into_iter()
+                .filter(|(_, order)| order.paid == paid)
+                .collect()
+        }
+
+        /// Get the number of orders in each status
+        #[ink(message)]
+        pub fn status_summary(&self) -> Vec<(OrderStatus, u32)> {
+            OrderStatus::variants()
+                .into_iter()
+                .map(|status| (status, self.status_counts.get(status).unwrap_or(0)))
+                .collect()
+        }
+
+        /// Cancel an order, refunding it to `refund_to` or else to the customer
+        fn cancel(&mut self, id: u32, refund_to: Option<AccountId>) -> Result<()> {
+            self.ensure_refunds_enabled()?;
+
+            // Get the caller account id
+            let caller = self.env().caller();
+
+            // get the order to cancel
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+
+            // only the customer can cancel an undelivered order
+            if caller != order.customer || order.status == OrderStatus::Delivered {
+                return Err(BurgerShopError::NotCancellable);
+            }
+            if order.disputed {
+                return Err(BurgerShopError::OrderDisputed);
+            }
+
+            // refund everything paid so far, less the service fee already passed
+            // on and any cancellation fee
+            let fee = self.cancellation_fee(&order)?;
+            let refund = order
+                .paid_amount
+                .saturating_sub(order.service_fee)
+                .saturating_sub(self.chain_price(fee)?);
+            self.refund_payers(&order, refund_to.unwrap_or(order.customer), refund)?;
+
+            // the refunded sale no longer counts as revenue, or is no longer held
+            if order.escrow > 0 {
+                let escrow = order.escrow;
+                self.drop_escrow(&mut order, escrow);
+            } else if order.paid && !order.comped {
+                self.reverse_sale(&order);
+            }
+
+            // the shop keeps the cancellation fee
+            self.add_revenue(order.outlet, fee)?;
+
+            // put the items back in stock
+            self.restore_stock(order.outlet, &order.list_of_items);
+
+            // remove from storage
+            self.remove_order(&order);
+
+            Ok(())
+        }
+
+        /// Send part of an order's payment back to whoever paid it. Split orders and
+        /// reservations refund each payer in proportion to what they contributed,
+        /// with the customer's own part going to `refund_to`.
+        fn refund_payers(
+            &mut self,
+            order: &Order,
+            refund_to: AccountId,
+            refund: Balance,
+        ) -> Result<()> {
+            let payers = self.split_payers.get(order.order_id).unwrap_or_default();
+            if payers.is_empty() {
+                return self.send_refund(order, refund_to, refund);
+            }
+
+            // give any rounding remainder to the last payer
+            let mut remaining = refund;
+            for (i, payer) in payers.iter().enumerate() {
+                let share = if i + 1 == payers.len() {
+                    remaining
+                } else {
+                    self.contributions
+                        .get((order.order_id, *payer))
+                        .unwrap_or(0)
+                        .checked_mul(refund)
+                        .ok_or(BurgerShopError::Overflow)?
+                        .checked_div(order.paid_amount)
+                        .unwrap_or(0)
+                        .min(remaining)
+                };
+                remaining -= share;
+
+                let to = if *payer == order.customer {
+                    refund_to
+                } else {
+                    *payer
+                };
+                self.send_refund(order, to, share)?;
+            }
+            Ok(())
+        }
+
+        /// Add a payment towards an order to what the payer has contributed
+        fn record_payment(
+            &mut self,
+            order_id: u32,
+            payer: AccountId,
+            amount: Balance,
+        ) -> Result<()> {
+            if amount == 0 {
+                return Ok(());
+            }
+
+            let key = (order_id, payer);
+            let contributed = self
+                .contributions
+                .get(key)
+                .unwrap_or(0)
+                .checked_add(amount)
+                .ok_or(BurgerShopError::Overflow)?;
+            self.contributions.insert(key, &contributed);
+
+            let mut payers = self.split_payers.get(order_id).unwrap_or_default();
+            if !payers.contains(&payer) {
+                payers.push(payer);
+                self.split_payers.insert(order_id, &payers);
+            }
+            Ok(())
+        }
+
+        /// Refund an amount from an order to an account, emitting the refund events
+        fn send_refund(&mut self, order: &Order, to: AccountId, amount: Balance) -> Result<()> {
+            if amount == 0 {
+                return Ok(());
+            }
+            self.refund(order, to, amount)?;
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(self.env().account_id()),
+                to: Some(to),
+                value: amount,
+            });
+            self.env().emit_event(Refunded {
+                order_id: order.order_id,
+                customer: order.customer,
+                amount,
+            });
+
+            Ok(())
+        }
+
+        /// Pass the service fee on a sold order's food subtotal to the fee
+        /// recipient, in the currency the order was paid with
+        fn pay_service_fee(&mut self, order: &mut Order) -> Result<()> {
+            let fee = self
+                .chain_price(order.total_price)?
+                .checked_mul(Balance::from(self.fee_basis_points))
+                .map(|fee| fee / BASIS_POINTS)
+                .ok_or(BurgerShopError::Overflow)?;
+            if fee == 0 {
+                return Ok(());
+            }
+
+            match order.payment_token {
+                Some(token) => self.token_transfer(token, self.fee_recipient, fee)?,
+                None => {
+                    if self.env().transfer(self.fee_recipient, fee).is_err() {
+                        return Err(BurgerShopError::PaymentError);
+                    }
+                }
+            }
+            order.service_fee = fee;
+
+            // Emit event
+            self.env().emit_event(Transfer {
+                from: Some(self.env().account_id()),
+                to: Some(self.fee_recipient),
+                value: fee,
+            });
+
+            Ok(())
+        }
+
+        /// Hold the payment for a fully paid order in escrow until the customer
+        /// confirms delivery
+        fn settle(&mut self, order: &mut Order) -> Result<()> {
+            order.escrow = order.paid_amount;
+            if order.escrow == 0 {
+                return self.record_sale(order);
+            }
+
+            // only native payments are held in the contract's own balance
+            if order.payment_token.is_none() {
+                self.escrowed = self
+                    .escrowed
+                    .checked_add(order.escrow)
+                    .ok_or(BurgerShopError::Overflow)?;
+            }
+            Ok(())
+        }
+
+        /// Take part of an order's escrowed payment out of escrow
+        fn drop_escrow(&mut self, order: &mut Order, amount: Balance) {
+            let amount = amount.min(order.escrow);
+            order.escrow -= amount;
+            if order.payment_token.is_none() {
+                self.escrowed = self.escrowed.saturating_sub(amount);
+            }
+        }
+
+        /// Release an order's escrowed payment to the shop, passing on the service
+        /// fee and counting it as a sale
+        fn release_escrow(&mut self, order: &mut Order) -> Result<()> {
+            if order.escrow == 0 {
+                return Err(BurgerShopError::NotEscrowed);
+            }
+            let escrow = order.escrow;
+            self.drop_escrow(order, escrow);
+            self.pay_service_fee(order)?;
+
+            // record the sale
+            self.record_sale(order)
+        }
+
+        /// Ensure the caller is the shop owner
+        fn ensure_owner(&self) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(BurgerShopError::NotOwner);
+            }
+            Ok(())
+        }
+
+        /// Move an order to the next stage of its lifecycle
+        fn advance(&mut self, id: u32) -> Result<OrderStatus> {
+            // get the order to advance
+            let mut order = self
+                .orders_mapping
+                .get(id)
+                .ok_or(BurgerShopError::OrderNotFound)?;
+
+            // the kitchen only works on paid orders
+            if !order.paid {
+                return Err(BurgerShopError::NotFullyPaid);
+            }
+
+            // delivered orders can't move any further
+            let status = order
+                .status
+                .next()
+                .ok_or(BurgerShopError::InvalidStatusTransition)?;
+            self.set_status(&mut order, status);
+
+            // update storage
+            self.orders_mapping.insert(id, &order);
+
+            Ok(status)
+        }
+
+        /// Ensure the shop is currently giving refunds
+        fn ensure_refunds_enabled(&self) -> Result<()> {
+            if !self.refunds_enabled {
+                return Err(BurgerShopError::RefundsDisabled);
+            }
+            Ok(())
+        }
+
+        /// Ensure an outlet id belongs to a registered outlet
+        fn ensure_outlet(&self, outlet: u32) -> Result<()> {
+            if !self.outlets.contains(outlet) {
+                return Err(BurgerShopError::UnknownOutlet);
+            }
+            Ok(())
+        }
+
+        /// Send a refund in the currency the order was paid with
+        fn refund(&mut self, order: &Order, to: AccountId, amount: Balance) -> Result<()> {
+            // cap the total refunded within a single block
+            let block = self.env().block_number();
+            if block != self.refund_block {
+                self.refund_block = block;
+                self.refunded_in_block = 0;
+            }
+            let refunded = self
+                .refunded_in_block
+                .checked_add(amount)
+                .ok_or(BurgerShopError::Overflow)?;
+            if refunded > self.max_refund_per_block {
+                return Err(BurgerShopError::RefundRateLimited);
+            }
+            self.refunded_in_block = refunded;
+
+            match order.payment_token {
+                Some(token) => self.token_transfer(token, to, amount),
+                None => self
+                    .env()
+                    .transfer(to, amount)
+                    .map_err(|_| BurgerShopError::PaymentError),
+            }
+        }
+
+        /// Call `PSP22::transfer_from` on a token to pull funds into the shop
+        fn token_transfer_from(
+            &self,
+            token: AccountId,
+            from: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let result = build_call::<Environment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!(
+                        "PSP22::transfer_from"
+                    )))
+                    .push_arg(from)
+                    .push_arg(self.env().account_id())
+                    .push_arg(value)
+                    .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<core::result::Result<(), Psp22Error>>()
+                .try_invoke();
+
+            match result {
+                Ok(Ok(Ok(()))) => Ok(()),
+                _ => Err(BurgerShopError::TokenTransferFailed),
+            }
+        }
+
+        /// Call `PSP22::transfer` on a token to send funds out of the shop
+        fn token_transfer(&self, token: AccountId, to: AccountId, value: Balance) -> Result<()> {
+            let result = build_call::<Environment>()
+                .call(token)
+                .gas_limit(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("PSP22::transfer")))
+                        .push_arg(to)
+                        .push_arg(value)
+                        .push_arg(Vec::<u8>::new()),
+                )
+                .returns::<core::result::Result<(), Psp22Error>>()
+                .try_invoke();
+
+            match result {
+                Ok(Ok(Ok(()))) => Ok(()),
+                _ => Err(BurgerShopError::TokenTransferFailed),
+            }
+        }
+
+        /// Convert a menu price into the on-chain value to pay
+        fn chain_price(&self, price: Balance) -> Result<Balance> {
+            price
+                .checked_mul(self.price_multiplier)
+                .ok_or(BurgerShopError::Overflow)
+        }
+
+        /// Reduce a price by the given percentage
+        fn apply_discount(&self, price: Balance, percent: u8) -> Result<Balance> {
+            let discount = price
+                .checked_mul(percent as u128)
+                .map(|discount| self.rounding_mode.divide(discount, 100))
+                .ok_or(BurgerShopError::Overflow)?;
+            Ok(price.saturating_sub(discount))
+        }
+
+        /// Price an order the way it is charged: surge pricing, then the coupon and
+        /// happy hour discounts, then up to `loyalty_discount` of redeemed points.
+        /// Returns the price and the loyalty discount used.
+        fn price_items(
+            &self,
+            price: Balance,
+            coupon_percent: u8,
+            loyalty_discount: Balance,
+            pending: u32,
+        ) -> Result<(Balance, Balance)> {
+            // Raise the price while the kitchen is busy
+            let mut price = self.apply_surge(price, pending)?;
+
+            // Apply the coupon discount
+            price = self.apply_discount(price, coupon_percent)?;
+
+            // Apply the happy hour discount
+            if self.is_happy_hour() {
+                price = self.apply_discount(price, self.happy_hour_percent)?;
+            }
+
+            // Apply any redeemed loyalty points
+            let loyalty_discount = loyalty_discount.min(price);
+            Ok((price - loyalty_discount, loyalty_discount))
+        }
+
+        /// Raise a price by the surge rate for every pending order, up to the surge
+        /// cap
+        fn apply_surge(&self, price: Balance, pending: u32) -> Result<Balance> {
+            let surge = Balance::from(pending)
+                .saturating_mul(Balance::from(self.surge_basis_points_per_pending))
+                .min(Balance::from(self.max_surge_basis_points));
+
+            price
+                .checked_mul(BASIS_POINTS + surge)
+                .map(|surged| surged / BASIS_POINTS)
+                .ok_or(BurgerShopError::Overflow)
+        }
+
+        /// Count the orders not yet delivered
+        fn pending_orders(&self) -> u32 {
+            let delivered = self.status_counts.get(OrderStatus::Delivered).unwrap_or(0);
+            self.order_count.saturating_sub(delivered)
+        }
+
+        /// Check whether an order is paid for in shares by several payers
+        fn is_split_order(&self, id: u32) -> bool {
+            self.split_payers
+                .get(id)
+                .unwrap_or_default()
+                .iter()
+                .any(|payer| self.split_shares.contains((id, *payer)))
+        }
+
+        /// Check whether the current block falls inside the happy hour window
+        fn is_happy_hour(&self) -> bool {
+            if self.happy_hour_percent == 0 {
+                return false;
+            }
+
+            let now = (self.env().block_timestamp() / 1000 % u64::from(SECONDS_PER_DAY)) as u32;
+            if self.happy_hour_start <= self.happy_hour_end {
+                (self.happy_hour_start..self.happy_hour_end).contains(&now)
+            } else {
+                // the window wraps past midnight
+                now >= self.happy_hour_start || now < self.happy_hour_end
+            }
+        }
+
+        /// Ensure an order's item total meets the shop minimum
+        fn ensure_min_order_value(&self, total_price: Balance) -> Result<()> {
+            if total_price < self.min_order_value {
+                return Err(BurgerShopError::BelowMinimum);
+            }
+            Ok(())
+        }
+
+        /// Compute the sales tax owed on a price at the current rate
+        fn tax_on(&self, price: Balance) -> Result<Balance> {
+            price
+                .checked_mul(Balance::from(self.tax_basis_points))
+                .map(|taxed| self.rounding_mode.divide(taxed, BASIS_POINTS))
+                .ok_or(BurgerShopError::Overflow)
+        }
+
+        /// Compute the fee, in menu price units, kept when cancelling an order.
+        /// Cancelling is free within the grace period or before the kitchen starts.
+        fn cancellation_fee(&self, order: &Order) -> Result<Balance> {
+            let grace_end = order.created_at.saturating_add(self.cancel_grace_period);
+            if !order.paid
+                || order.comped
+                || order.status == OrderStatus::Paid
+                || self.env().block_timestamp() <= grace_end
+            {
+                return Ok(0);
+            }
+
+            order
+                .amount_due()?
+                .checked_mul(Balance::from(self.cancellation_fee_basis_points))
+                .map(|fee| self.rounding_mode.divide(fee, BASIS_POINTS))
+                .ok_or(BurgerShopError::Overflow)
+        }
+
+        /// Ensure every item in a list is for at least one burger
+        fn ensure_no_zero_amounts(list_of_items: &[FoodItem]) -> Result<()> {
+            if list_of_items.iter().any(|item| item.amount == 0) {
+                return Err(BurgerShopError::ZeroAmountItem);
+            }
+            Ok(())
+        }
+
+        /// Ensure an account argument isn't the zero account
+        fn ensure_not_zero(account: AccountId) -> Result<()> {
+            if account == AccountId::from([0u8; 32]) {
+                return Err(BurgerShopError::ZeroAddress);
+            }
+            Ok(())
+        }
+
+        /// Ensure the caller is the owner or holds one of the given staff roles
+        fn ensure_role(&self, allowed: &[Role]) -> Result<()> {
+            let caller = self.env().caller();
+            if caller == self.owner {
+                return Ok(());
+            }
+            match self.roles.get(caller) {
+                Some(role) if allowed.contains(&role) => Ok(()),
+                _ => Err(BurgerShopError::Unauthorized),
+            }
+        }
+
+        /// Get the configured price of a burger variant
+        fn price_of(&self, menu: &BurgerMenu) -> Result<Balance> {
+            match menu {
+                BurgerMenu::Special(id) => match self.menu_items.get(id) {
+                    Some(entry) if entry.available => Ok(entry.price),
+                    _ => Err(BurgerShopError::UnknownMenuItem),
+                },
+                _ => Ok(self.prices.get(menu).unwrap_or_else(|| menu.price())),
+            }
+        }
+
+        /// Compute the stock levels left at an outlet after taking the given items.
+        /// Burgers without a stock entry aren't tracked and never run out.
+        fn remaining_stock(
+            &self,
+            outlet: u32,
+            list_of_items: &[FoodItem],
+        ) -> Result<Vec<(BurgerMenu, u32)>> {
+            let mut levels: Vec<(BurgerMenu, u32)> = Vec::new();
+            for item in list_of_items {
+                let index = match levels
+                    .iter()
+                    .position(|(menu, _)| *menu == item.burger_menu)
+                {
+                    Some(index) => index,
+                    None => match self.stock.get((outlet, item.burger_menu.clone())) {
+                        Some(level) => {
+                            levels.push((item.burger_menu.clone(), level));
+                            levels.len() - 1
+                        }
+                        None => continue,
+                    },
+                };
+
+                // reject the whole order if any burger runs out
+                let level = &mut levels[index].1;
+                *level = level
+                    .checked_sub(item.amount)
+                    .ok_or(BurgerShopError::OutOfStock {
+                        menu: item.burger_menu.clone(),
+                    })?;
+            }
+            Ok(levels)
+        }
+
+        /// Return the given items to their tracked stock at an outlet
+        fn restore_stock(&mut self, outlet: u32, list_of_items: &[FoodItem]) {
+            for item in list_of_items {
+                let key = (outlet, item.burger_menu.clone());
+                if let Some(level) = self.stock.get(&key) {
+                    self.stock.insert(&key, &level.saturating_add(item.amount));
+                }
+            }
+        }
+
+        /// Count a fully paid order towards revenue
+        fn record_sale(&mut self, order: &Order) -> Result<()> {
+            let amount = order.amount_due()?;
+            self.add_revenue(order.outlet, amount)?;
+            let spend = self
+                .customer_spend(order.customer)
+                .checked_add(amount)
+                .ok_or(BurgerShopError::Overflow)?;
+            self.customer_spend.insert(order.customer, &spend);
+            self.total_tips = self
+                .total_tips
+                .checked_add(order.tip)
+                .ok_or(BurgerShopError::Overflow)?;
+            self.total_tax = self
+                .total_tax
+                .checked_add(order.tax)
+                .ok_or(BurgerShopError::Overflow)?;
+
+            // reward the customer with loyalty points
+            let points = order
+                .total_price
+                .checked_mul(self.points_per_unit)
+                .and_then(|earned| earned.checked_add(self.points_of(order.customer)))
+                .ok_or(BurgerShopError::Overflow)?;
+            self.loyalty_points.insert(order.customer, &points);
+
+            // attribute the item total to each burger sold
+            let shares = Self::split_item_revenue(order)?;
+            for (menu, share) in &shares {
+                let key = (order.outlet, menu.clone());
+                let revenue = self
+                    .item_revenue
+                    .get(&key)
+                    .unwrap_or(0)
+                    .checked_add(*share)
+                    .ok_or(BurgerShopError::Overflow)?;
+                self.item_revenue.insert(&key, &revenue);
+            }
+            self.order_item_revenue.insert(order.order_id, &shares);
+            self.sales_count = self
+                .sales_count
+                .checked_add(1)
+                .ok_or(BurgerShopError::Overflow)?;
+
+            Ok(())
+        }
+
+        /// Take a refunded order back out of the revenue totals
+        fn reverse_sale(&mut self, order: &Order) {
+            let deposit = if order.deposit_returned {
+                0
+            } else {
+                order.deposit
+            };
+            // the service fee was passed on and isn't refunded, so it stays counted
+            let amount = order
+                .total_price
+                .saturating_add(order.tip)
+                .saturating_add(order.tax)
+                .saturating_add(deposit)
+                .saturating_sub(order.service_fee / self.price_multiplier);
+            self.reduce_revenue(order.outlet, amount);
+            self.reduce_spend(order.customer, amount);
+            self.total_tips = self.total_tips.saturating_sub(order.tip);
+            self.total_tax = self.total_tax.saturating_sub(order.tax);
+            self.refund_item_revenue(order, order.total_price);
+            self.order_item_revenue.remove(order.order_id);
+            self.sales_count = self.sales_count.saturating_sub(1);
+        }
+
+        /// Split an order's item total across its burgers, as on its receipt
+        fn split_item_revenue(order: &Order) -> Result<ItemRevenue> {
+            Ok(order
+                .list_of_items
+                .iter()
+                .map(|item| item.burger_menu.clone())
+                .zip(order.line_totals()?)
+                .collect())
+        }
+
+        /// Take a refunded part of an order's item total back out of the per-item
+        /// revenue, in proportion to what each burger contributed
+        fn refund_item_revenue(&mut self, order: &Order, refunded: Balance) {
+            let id = order.order_id;
+            let mut shares = self.order_item_revenue.get(id).unwrap_or_default();
+            for (menu, share) in shares.iter_mut() {
+                let deduction = share
+                    .saturating_mul(refunded)
+                    .checked_div(order.total_price)
+                    .unwrap_or(0);
+                let key = (order.outlet, menu.clone());
+                let revenue = self.item_revenue.get(&key).unwrap_or(0);
+                self.item_revenue
+                    .insert(&key, &revenue.saturating_sub(deduction));
+                *share -= deduction;
+            }
+            self.order_item_revenue.insert(id, &shares);
+        }
+
+        /// Add an amount to the shop and outlet revenue totals
+        fn add_revenue(&mut self, outlet: u32, amount: Balance) -> Result<()> {
+            self.total_revenue = self
+                .total_revenue
+                .checked_add(amount)
+                .ok_or(BurgerShopError::Overflow)?;
+            let revenue = self
+                .outlet_revenue
+                .get(outlet)
+                .unwrap_or(0)
+                .checked_add(amount)
+                .ok_or(BurgerShopError::Overflow)?;
+            self.outlet_revenue.insert(outlet, &revenue);
+            Ok(())
+        }
+
+        /// Take a refunded amount off the shop and outlet revenue totals
+        fn reduce_revenue(&mut self, outlet: u32, amount: Balance) {
+            self.total_revenue = self.total_revenue.saturating_sub(amount);
+            let revenue = self.outlet_revenue.get(outlet).unwrap_or(0);
+            self.outlet_revenue
+                .insert(outlet, &revenue.saturating_sub(amount));
+        }
+
+        /// Take a refunded amount off a customer's total spend
+        fn reduce_spend(&mut self, who: AccountId, amount: Balance) {
+            let spend = self.customer_spend(who).saturating_sub(amount);
+            self.customer_spend.insert(who, &spend);
+        }
+
+        /// Store a newly created order, take its items out of stock and index it
+        fn insert_order(&mut self, order: &Order, remaining_stock: Vec<(BurgerMenu, u32)>) {
+            // take the items out of stock
+            for (menu, level) in remaining_stock {
+                let key = (order.outlet, menu.clone());
+                let previous = self.stock.get(&key).unwrap_or(0);
+                self.stock.insert(&key, &level);
+
+                // alert when the stock first drops to its reorder point
+                if let Some(point) = self.reorder_points.get(&key) {
+                    if previous > point && level <= point {
+                        self.env().emit_event(LowStockAlert {
+                            outlet: order.outlet,
+                            menu,
+                            remaining: level,
+                        });
+                    }
+                }
+            }
+
+            // use up the redeemed loyalty discount
+            if order.loyalty_discount > 0 {
+                let remaining = self.loyalty_discounts.get(order.customer).unwrap_or(0)
+                    - order.loyalty_discount;
+                self.loyalty_discounts.insert(order.customer, &remaining);
+            }
+
+            // count the order towards the customer's daily cap
+            let day = order.created_at / MILLIS_PER_DAY;
+            let daily = self.daily_orders.get((order.customer, day)).unwrap_or(0);
+            self.daily_orders
+                .insert((order.customer, day), &daily.saturating_add(1));
+
+            let id = order.order_id;
+            self.next_order_id = id + 1;
+            self.orders_mapping.insert(id, order);
+            self.order_ids.push(id);
+            self.order_count += 1;
+
+            // index the order under its customer
+            let mut customer_ids = self.customer_orders.get(order.customer).unwrap_or_default();
+            customer_ids.push(id);
+            self.customer_orders.insert(order.customer, &customer_ids);
+            self.index_status(order.status, id);
+
+            // index the order under its id within the outlet
+            self.outlet_orders
+                .insert((order.outlet, order.outlet_order_id), &id);
+            self.next_outlet_order_ids
+                .insert(order.outlet, &order.outlet_order_id.saturating_add(1));
+
+            // Emit event
+            self.env().emit_event(OrderCreated {
+                order_id: id,
+                customer: order.customer,
+                total_price: order.total_price,
+            });
+        }
+
+        /// Remove an order from storage and its indexes
+        fn remove_order(&mut self, order: &Order) {
+            let id = order.order_id;
+            self.orders_mapping.remove(id);
+            self.order_ids.retain(|order_id| *order_id != id);
+            self.order_count -= 1;
+
+            let mut customer_ids = self.customer_orders.get(order.customer).unwrap_or_default();
+            customer_ids.retain(|order_id| *order_id != id);
+            self.customer_orders.insert(order.customer, &customer_ids);
+            self.unindex_status(order.status, id);
+            self.outlet_orders
+                .remove((order.outlet, order.outlet_order_id));
+
+            // forget what each payer owed and paid
+            for payer in self.split_payers.take(id).unwrap_or_default() {
+                self.split_shares.remove((id, payer));
+                self.contributions.remove((id, payer));
+            }
+
+            // Emit event
+            self.env().emit_event(OrderRemoved {
+                order_id: id,
+                customer: order.customer,
+            });
+        }
+
+        /// Move an order to a new status, keeping the status index in sync
+        fn set_status(&mut self, order: &mut Order, status: OrderStatus) {
+            self.unindex_status(order.status, order.order_id);
+            order.status = status;
+            self.index_status(status, order.order_id);
+            if status == OrderStatus::Delivered {
+                order.delivered_at = Some(self.env().block_timestamp());
+            }
+
+            // emit event
+            self.env().emit_event(OrderStatusChanged {
+                order_id: order.order_id,
+                status,
+            });
+        }
+
+        /// Add an order id to the index of its status
+        fn index_status(&mut self, status: OrderStatus, id: u32) {
+            let mut ids = self.status_orders.get(status).unwrap_or_default();
+            ids.push(id);
+            self.status_orders.insert(status, &ids);
+            let count = self.status_counts.get(status).unwrap_or(0);
+            self.status_counts.insert(status, &count.saturating_add(1));
+        }
+
+        /// Drop an order id from the index of its status
+        fn unindex_status(&mut self, status: OrderStatus, id: u32) {
+            let mut ids = self.status_orders.get(status).unwrap_or_default();
+            ids.retain(|order_id| *order_id != id);
+            self.status_orders.insert(status, &ids);
+            let count = self.status_counts.get(status).unwrap_or(0);
+            self.status_counts.insert(status, &count.saturating_sub(1));
+        }
+
+        /// Read the orders for the given ids from storage
+        fn collect_orders<'a>(&self, ids: impl Iterator<Item = &'a u32>) -> Vec<(u32, Order)> {
+            ids.filter_map(|id| self.orders_mapping.get(id).map(|order| (*id, order)))
+                .collect()
+        }
+    }
+
+    /// Unit tests in Rust are normally defined within such a `#[cfg(test)]`
+    /// module and test functions are marked with a `#[test]` attribute.
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink::env::{test, DefaultEnvironment};
+
+        /// Account the shop contract is deployed at
+        const SHOP: [u8; 32] = [0xFF; 32];
+
+        /// Balance the shop starts with, so refunds can be paid out
+        const SHOP_FUNDS: Balance = 1_000_000;
+
+        fn accounts() -> test::DefaultAccounts<DefaultEnvironment> {
+            test::default_accounts::<DefaultEnvironment>()
+        }
+
+        fn set_caller(caller: AccountId) {
+            test::set_caller::<DefaultEnvironment>(caller);
+        }
+
+        fn set_value(value: Balance) {
+            test::set_value_transferred::<DefaultEnvironment>(value);
+        }
+
+        /// Deploy a funded shop owned by alice, where one menu price unit is one
+        /// unit of on-chain value
+        fn shop() -> BurgerShop {
+            let shop = AccountId::from(SHOP);
+            test::set_callee::<DefaultEnvironment>(shop);
+            test::set_account_balance::<DefaultEnvironment>(shop, SHOP_FUNDS);
+            set_caller(accounts().alice);
+            BurgerShop::new_with_multiplier(1).unwrap()
+        }
+
+        fn item(burger_menu: BurgerMenu, amount: u32) -> FoodItem {
+            FoodItem {
+                burger_menu,
+                amount,
+            }
+        }
+
+        #[ink::test]
+        fn underpayment_reports_expected_and_received() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(10);
+
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 2)])
+                    .unwrap_err(),
+                BurgerShopError::IncorrectPayment {
+                    expected: 24,
+                    received: 10,
+                }
+            );
+            assert_eq!(shop.order_count(), 0);
+        }
+
+        #[ink::test]
+        fn missing_payment_reports_nothing_received() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::ChickenBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::IncorrectPayment {
+                    expected: 15,
+                    received: 0,
+                }
+            );
+        }
+
+        #[ink::test]
+        fn empty_order_is_rejected() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+
+            assert_eq!(
+                shop.take_order_and_payment(Vec::new()).unwrap_err(),
+                BurgerShopError::EmptyOrder
+            );
+        }
+
+        fn balance_of(account: AccountId) -> Balance {
+            test::get_account_balance::<DefaultEnvironment>(account).unwrap()
+        }
+
+        /// Place an order as `customer`, paying exactly what it costs
+        fn place(shop: &mut BurgerShop, customer: AccountId, items: Vec<FoodItem>) -> Order {
+            set_caller(customer);
+            set_value(shop.estimate_order_price(items.clone()).unwrap());
+            let order = shop.take_order_and_payment(items).unwrap();
+            set_value(0);
+            order
+        }
+
+        #[ink::test]
+        fn cancel_refunds_the_customer() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            let before = balance_of(bob);
+
+            assert_eq!(shop.cancel_order(order.order_id), Ok(()));
+            assert_eq!(balance_of(bob), before + 24);
+            assert_eq!(
+                shop.get_single_order(order.order_id).unwrap_err(),
+                BurgerShopError::OrderNotFound
+            );
+        }
+
+        #[ink::test]
+        fn only_the_customer_can_cancel() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+
+            set_caller(accounts().charlie);
+            assert_eq!(
+                shop.cancel_order(order.order_id),
+                Err(BurgerShopError::NotCancellable)
+            );
+        }
+
+        #[ink::test]
+        fn delivered_orders_cannot_be_cancelled() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+
+            set_caller(accounts().alice);
+            for _ in 0..3 {
+                shop.advance_order_status(order.order_id).unwrap();
+            }
+
+            set_caller(bob);
+            assert_eq!(
+                shop.cancel_order(order.order_id),
+                Err(BurgerShopError::NotCancellable)
+            );
+        }
+
+        #[ink::test]
+        fn order_status_walks_through_the_lifecycle() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::VeggieBurger, 1)],
+            );
+            let id = order.order_id;
+            assert_eq!(order.status, OrderStatus::Paid);
+
+            set_caller(accounts().alice);
+            assert_eq!(shop.advance_order_status(id), Ok(OrderStatus::Preparing));
+            assert_eq!(shop.advance_order_status(id), Ok(OrderStatus::Ready));
+            assert_eq!(shop.advance_order_status(id), Ok(OrderStatus::Delivered));
+            assert_eq!(
+                shop.advance_order_status(id),
+                Err(BurgerShopError::InvalidStatusTransition)
+            );
+        }
+
+        #[ink::test]
+        fn only_the_owner_can_list_all_orders() {
+            let mut shop = shop();
+            place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+
+            assert_eq!(shop.get_orders().unwrap_err(), BurgerShopError::NotOwner);
+
+            set_caller(accounts().alice);
+            let orders = shop.get_orders().unwrap().unwrap();
+            assert_eq!(orders.len(), 1);
+            assert_eq!(orders[0].1.customer, accounts().bob);
+        }
+
+        #[ink::test]
+        fn orders_use_the_updated_price() {
+            let mut shop = shop();
+            assert_eq!(shop.set_price(BurgerMenu::CheeseBurger, 20), Ok(()));
+
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 2)],
+            );
+            assert_eq!(order.total_price, 40);
+        }
+
+        #[ink::test]
+        fn only_the_owner_can_set_prices() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+
+            assert_eq!(
+                shop.set_price(BurgerMenu::CheeseBurger, 1),
+                Err(BurgerShopError::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn specials_can_be_ordered_until_disabled() {
+            let mut shop = shop();
+            let id = shop.add_menu_item(b"smash".to_vec(), 18).unwrap();
+            let special = BurgerMenu::Special(id);
+
+            let order = place(&mut shop, accounts().bob, vec![item(special.clone(), 2)]);
+            assert_eq!(order.total_price, 36);
+
+            set_caller(accounts().alice);
+            assert_eq!(shop.disable_menu_item(id), Ok(()));
+
+            set_caller(accounts().bob);
+            set_value(18);
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(special, 1)])
+                    .unwrap_err(),
+                BurgerShopError::UnknownMenuItem
+            );
+        }
+
+        #[ink::test]
+        fn withdraw_sends_part_of_the_balance_to_the_owner() {
+            let mut shop = shop();
+            let alice = accounts().alice;
+            let before = balance_of(alice);
+
+            assert_eq!(shop.withdraw(400_000), Ok(()));
+            assert_eq!(shop.shop_balance(), SHOP_FUNDS - 400_000);
+            assert_eq!(balance_of(alice), before + 400_000);
+
+            assert_eq!(
+                shop.withdraw(SHOP_FUNDS),
+                Err(BurgerShopError::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn orders_are_paged_by_start_and_limit() {
+            let mut shop = shop();
+            for _ in 0..100 {
+                place(
+                    &mut shop,
+                    accounts().bob,
+                    vec![item(BurgerMenu::VeggieBurger, 1)],
+                );
+            }
+            let ids = |page: Vec<(u32, Order)>| -> Vec<u32> {
+                page.into_iter().map(|(id, _)| id).collect()
+            };
+
+            assert_eq!(ids(shop.get_orders_paged(0, 3)), vec![0, 1, 2]);
+            assert_eq!(ids(shop.get_orders_paged(97, 10)), vec![97, 98, 99]);
+            assert_eq!(shop.get_orders_paged(10, 100).len(), MAX_PAGE_SIZE as usize);
+            assert!(shop.get_orders_paged(100, 10).is_empty());
+            assert!(shop.get_orders_paged(u32::MAX, 10).is_empty());
+        }
+
+        #[ink::test]
+        fn customers_see_only_their_own_orders() {
+            let mut shop = shop();
+            let accounts = accounts();
+            let first = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+            place(
+                &mut shop,
+                accounts.charlie,
+                vec![item(BurgerMenu::ChickenBurger, 1)],
+            );
+            let second = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::VeggieBurger, 1)],
+            );
+
+            let bobs: Vec<u32> = shop
+                .get_orders_by_customer(accounts.bob)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            assert_eq!(bobs, vec![first.order_id, second.order_id]);
+
+            let charlies = shop.get_orders_by_customer(accounts.charlie);
+            assert_eq!(charlies.len(), 1);
+            assert_eq!(charlies[0].1.customer, accounts.charlie);
+            assert!(shop.get_orders_by_customer(accounts.django).is_empty());
+        }
+
+        #[ink::test]
+        fn order_ids_match_the_stored_orders() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            for amount in 1..=3 {
+                place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, amount)]);
+            }
+
+            assert_eq!(shop.order_ids, vec![0, 1, 2]);
+            for id in &shop.order_ids {
+                assert_eq!(shop.orders_mapping.get(id).unwrap().order_id, *id);
+            }
+
+            let order = shop.get_single_order(1).unwrap();
+            assert_eq!(order.order_id, 1);
+            assert_eq!(order.customer, bob);
+            assert_eq!(order.list_of_items[0].amount, 2);
+            assert_eq!(order.total_price, 24);
+        }
+
+        #[ink::test]
+        fn the_multiplier_scales_the_expected_payment() {
+            test::set_callee::<DefaultEnvironment>(AccountId::from(SHOP));
+            let mut shop = BurgerShop::new_with_multiplier(1_000).unwrap();
+            set_caller(accounts().bob);
+            set_value(12);
+
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::IncorrectPayment {
+                    expected: 12_000,
+                    received: 12,
+                }
+            );
+        }
+
+        #[ink::test]
+        fn a_zero_multiplier_is_rejected() {
+            assert!(matches!(
+                BurgerShop::new_with_multiplier(0),
+                Err(BurgerShopError::InvalidMultiplier)
+            ));
+        }
+
+        #[ink::test]
+        fn huge_quantities_return_errors_instead_of_panicking() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            let huge = vec![item(BurgerMenu::CheeseBurger, u32::MAX)];
+
+            assert_eq!(
+                shop.take_order_and_payment(huge.clone()).unwrap_err(),
+                BurgerShopError::OrderTooLarge
+            );
+
+            // with the size cap lifted, pricing overflows instead
+            test::set_callee::<DefaultEnvironment>(AccountId::from(SHOP));
+            set_caller(accounts().alice);
+            let mut shop = BurgerShop::new_with_multiplier(Balance::MAX).unwrap();
+            shop.set_max_items_per_order(u32::MAX).unwrap();
+
+            set_caller(accounts().bob);
+            assert_eq!(
+                shop.take_order_and_payment(huge).unwrap_err(),
+                BurgerShopError::Overflow
+            );
+        }
+
+        type Event = <BurgerShop as ::ink::reflect::ContractEventBase>::Type;
+
+        /// Decode every event emitted so far
+        fn events() -> Vec<Event> {
+            test::recorded_events()
+                .map(|event| <Event as scale::Decode>::decode(&mut &event.data[..]).unwrap())
+                .collect()
+        }
+
+        #[ink::test]
+        fn placing_an_order_emits_order_created() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::ChickenBurger, 2)]);
+
+            let created: Vec<OrderCreated> = events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::OrderCreated(created) => Some(created),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(created.len(), 1);
+            assert_eq!(created[0].order_id, order.order_id);
+            assert_eq!(created[0].customer, bob);
+            assert_eq!(created[0].total_price, 30);
+        }
+
+        #[ink::test]
+        fn orders_take_items_out_of_stock() {
+            let mut shop = shop();
+            shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 5)
+                .unwrap();
+            shop.restock(MAIN_OUTLET, BurgerMenu::VeggieBurger, 1)
+                .unwrap();
+
+            place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 2)],
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(3))
+            );
+
+            // untracked burgers can still be ordered
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::ChickenBurger),
+                Ok(None)
+            );
+            place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::ChickenBurger, 4)],
+            );
+        }
+
+        #[ink::test]
+        fn sold_out_orders_leave_stock_untouched() {
+            let mut shop = shop();
+            shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 5)
+                .unwrap();
+            shop.restock(MAIN_OUTLET, BurgerMenu::VeggieBurger, 1)
+                .unwrap();
+
+            set_caller(accounts().bob);
+            set_value(32);
+            assert_eq!(
+                shop.take_order_and_payment(vec![
+                    item(BurgerMenu::CheeseBurger, 1),
+                    item(BurgerMenu::VeggieBurger, 2),
+                ])
+                .unwrap_err(),
+                BurgerShopError::OutOfStock {
+                    menu: BurgerMenu::VeggieBurger
+                }
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(5))
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::VeggieBurger),
+                Ok(Some(1))
+            );
+        }
+
+        #[ink::test]
+        fn revenue_adds_up_the_order_totals() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::ChickenBurger, 2)]);
+
+            // payments count as revenue once released from escrow
+            set_caller(bob);
+            shop.confirm_delivery(first.order_id).unwrap();
+            shop.confirm_delivery(second.order_id).unwrap();
+
+            set_caller(accounts().alice);
+            assert_eq!(
+                shop.get_total_revenue(),
+                Ok(first.total_price + second.total_price)
+            );
+        }
+
+        #[ink::test]
+        fn coupons_discount_the_payment_and_the_total() {
+            let mut shop = shop();
+            shop.create_coupon(b"QUARTER".to_vec(), 25).unwrap();
+            let items = vec![item(BurgerMenu::CheeseBurger, 4)];
+            let coupon = Some(b"QUARTER".to_vec());
+
+            set_caller(accounts().bob);
+            set_value(35);
+            assert_eq!(
+                shop.take_order_and_payment_with_coupon(items.clone(), coupon.clone())
+                    .unwrap_err(),
+                BurgerShopError::IncorrectPayment {
+                    expected: 36,
+                    received: 35,
+                }
+            );
+
+            set_value(36);
+            let order = shop
+                .take_order_and_payment_with_coupon(items, coupon)
+                .unwrap();
+            assert_eq!(order.total_price, 36);
+            assert_eq!(order.paid_amount, 36);
+        }
+
+        #[ink::test]
+        fn unknown_coupons_are_rejected() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(12);
+
+            assert_eq!(
+                shop.take_order_and_payment_with_coupon(
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    Some(b"NOPE".to_vec()),
+                )
+                .unwrap_err(),
+                BurgerShopError::InvalidCoupon
+            );
+        }
+
+        #[ink::test]
+        fn only_paid_ready_orders_can_be_fulfilled() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+
+            // an unpaid reservation
+            set_caller(bob);
+            set_value(5);
+            let reserved = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 1)])
+                .unwrap();
+            set_value(0);
+
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 1)]);
+
+            set_caller(accounts().alice);
+            assert_eq!(
+                shop.fulfill_order(reserved.order_id),
+                Err(BurgerShopError::OrderNotCompleted)
+            );
+            assert_eq!(
+                shop.fulfill_order(order.order_id),
+                Err(BurgerShopError::OrderNotCompleted)
+            );
+
+            shop.advance_order_status(order.order_id).unwrap();
+            shop.advance_order_status(order.order_id).unwrap();
+            assert_eq!(shop.fulfill_order(order.order_id), Ok(()));
+            assert_eq!(
+                shop.get_order_status(order.order_id),
+                Ok(OrderStatus::Delivered)
+            );
+        }
+
+        #[ink::test]
+        fn order_count_tracks_placed_and_cancelled_orders() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            assert_eq!(shop.order_count(), 0);
+
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            assert_eq!(shop.order_count(), 1);
+            place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            assert_eq!(shop.order_count(), 2);
+
+            set_caller(bob);
+            shop.cancel_order(first.order_id).unwrap();
+            assert_eq!(shop.order_count(), 1);
+        }
+
+        #[ink::test]
+        fn reservations_are_paid_off_in_instalments() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+
+            set_caller(bob);
+            set_value(10);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            assert!(!order.paid);
+            assert_eq!(order.status, OrderStatus::Reserved);
+
+            set_value(10);
+            let order = shop.pay_balance(order.order_id).unwrap();
+            assert!(!order.paid);
+            assert_eq!(order.paid_amount, 20);
+
+            // the last instalment overshoots the 24 owed by 6
+            let before = balance_of(bob);
+            set_value(10);
+            let order = shop.pay_balance(order.order_id).unwrap();
+            assert!(order.paid);
+            assert_eq!(order.status, OrderStatus::Paid);
+            assert_eq!(order.paid_amount, 24);
+            assert_eq!(balance_of(bob), before + 6);
+
+            assert_eq!(
+                shop.pay_balance(order.order_id).unwrap_err(),
+                BurgerShopError::AlreadyPaid
+            );
+        }
+
+        #[ink::test]
+        fn reservations_need_a_deposit() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+
+            set_caller(bob);
+            set_value(0);
+            assert_eq!(
+                shop.deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::ZeroDeposit
+            );
+            assert_eq!(shop.order_count(), 0);
+        }
+
+        #[ink::test]
+        fn only_the_owner_can_clear_orders() {
+            let mut shop = shop();
+            shop.set_price(BurgerMenu::VeggieBurger, 11).unwrap();
+            place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+            place(
+                &mut shop,
+                accounts().charlie,
+                vec![item(BurgerMenu::VeggieBurger, 1)],
+            );
+
+            assert_eq!(shop.clear_all_orders(), Err(BurgerShopError::NotOwner));
+            assert_eq!(shop.order_count(), 2);
+
+            set_caller(accounts().alice);
+            assert_eq!(shop.clear_all_orders(), Ok(()));
+            assert_eq!(shop.order_count(), 0);
+            assert!(shop.get_orders().unwrap().is_none());
+            assert!(shop.get_orders_by_customer(accounts().bob).is_empty());
+            assert_eq!(shop.price_of(&BurgerMenu::VeggieBurger), Ok(11));
+            assert_eq!(shop.price_of(&BurgerMenu::CheeseBurger), Ok(12));
+        }
+
+        #[ink::test]
+        fn clearing_orders_refunds_unreleased_payments() {
+            let mut shop = shop();
+            let accounts = accounts();
+            let escrowed = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+            let delivered = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::VeggieBurger, 1)],
+            );
+            confirm(&mut shop, &delivered);
+
+            // a reservation with a deposit in, and the balance partly paid by eve
+            set_caller(accounts.charlie);
+            set_value(5);
+            let reserved = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            set_caller(accounts.eve);
+            set_value(10);
+            shop.pay_balance(reserved.order_id).unwrap();
+            set_value(0);
+
+            let bob_before = balance_of(accounts.bob);
+            let charlie_before = balance_of(accounts.charlie);
+            let eve_before = balance_of(accounts.eve);
+            set_caller(accounts.alice);
+            assert_eq!(shop.clear_all_orders(), Ok(()));
+            assert_eq!(balance_of(accounts.bob), bob_before + escrowed.paid_amount);
+            assert_eq!(balance_of(accounts.charlie), charlie_before + 5);
+            assert_eq!(balance_of(accounts.eve), eve_before + 10);
+            assert_eq!(shop.escrowed_balance(), 0);
+            assert_eq!(shop.get_total_revenue(), Ok(delivered.total_price));
+        }
+
+        #[ink::test]
+        fn order_ids_keep_counting_after_a_clear() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            assert_eq!((first.order_id, first.outlet_order_id), (0, 0));
+
+            set_caller(accounts().alice);
+            shop.clear_all_orders().unwrap();
+
+            let next = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            assert_eq!((next.order_id, next.outlet_order_id), (1, 1));
+            assert_eq!(
+                shop.get_outlet_order(MAIN_OUTLET, 0).unwrap_err(),
+                BurgerShopError::OrderNotFound
+            );
+        }
+
+        #[ink::test]
+        fn deploying_emits_shop_initialized_once() {
+            let shop = shop();
+            assert_eq!(shop.owner, accounts().alice);
+
+            let initialized: Vec<ShopInitialized> = events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::ShopInitialized(initialized) => Some(initialized),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(initialized.len(), 1);
+            assert_eq!(initialized[0].owner, accounts().alice);
+            assert_eq!(initialized[0].price_multiplier, 1);
+        }
+
+        #[ink::test]
+        fn the_menu_lists_the_default_prices_and_updates() {
+            let mut shop = shop();
+            assert_eq!(
+                shop.get_menu(),
+                vec![
+                    (BurgerMenu::CheeseBurger, 12),
+                    (BurgerMenu::ChickenBurger, 15),
+                    (BurgerMenu::VeggieBurger, 10),
+                ]
+            );
+
+            shop.set_price(BurgerMenu::ChickenBurger, 17).unwrap();
+            assert!(shop.get_menu().contains(&(BurgerMenu::ChickenBurger, 17)));
+        }
+
+        #[ink::test]
+        fn missing_orders_are_not_found() {
+            let shop = shop();
+            assert_eq!(
+                shop.get_single_order(7).unwrap_err(),
+                BurgerShopError::OrderNotFound
+            );
+        }
+
+        fn set_time(timestamp: Timestamp) {
+            test::set_block_timestamp::<DefaultEnvironment>(timestamp);
+        }
+
+        #[ink::test]
+        fn orders_record_when_they_were_placed() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+
+            set_time(1_000);
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            set_time(5_000);
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+
+            assert_eq!(first.created_at, 1_000);
+            assert_eq!(second.created_at, 5_000);
+            assert_eq!(
+                shop.get_single_order(first.order_id).unwrap().created_at,
+                1_000
+            );
+        }
+
+        #[ink::test]
+        fn token_orders_refuse_native_value() {
+            test::set_callee::<DefaultEnvironment>(AccountId::from(SHOP));
+            let mut shop = BurgerShop::new_with_payment_token(accounts().eve);
+            set_caller(accounts().bob);
+            set_value(12);
+
+            // rejected before the token is ever called
+            assert_eq!(
+                shop.take_order_and_payment_psp22(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::IncorrectPayment {
+                    expected: 0,
+                    received: 12,
+                }
+            );
+        }
+
+        #[ink::test]
+        fn token_orders_fall_back_to_native_payment() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(12);
+
+            let order = shop
+                .take_order_and_payment_psp22(vec![item(BurgerMenu::CheeseBurger, 1)])
+                .unwrap();
+            assert!(order.paid);
+            assert_eq!(order.payment_token, None);
+        }
+
+        #[ink::test]
+        fn exact_payment_leaves_no_change() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let before = balance_of(bob);
+            set_caller(bob);
+            set_value(24);
+
+            let order = shop
+                .take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            assert!(order.paid);
+            assert_eq!(order.paid_amount, 24);
+            assert_eq!(balance_of(bob), before);
+        }
+
+        #[ink::test]
+        fn overpayment_is_handed_back() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let before = balance_of(bob);
+            set_caller(bob);
+            set_value(30);
+
+            let order = shop
+                .take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            assert_eq!(order.paid_amount, 24);
+            assert_eq!(balance_of(bob), before + 6);
+        }
+
+        #[ink::test]
+        fn underpayment_creates_no_order() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let before = balance_of(bob);
+            set_caller(bob);
+            set_value(23);
+
+            assert!(matches!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 2)]),
+                Err(BurgerShopError::IncorrectPayment { expected: 24, .. })
+            ));
+            assert_eq!(shop.order_count(), 0);
+            assert_eq!(balance_of(bob), before);
+        }
+
+        /// Confirm delivery as the order's customer, releasing its escrow as a sale
+        fn confirm(shop: &mut BurgerShop, order: &Order) {
+            set_caller(order.customer);
+            shop.confirm_delivery(order.order_id).unwrap();
+        }
+
+        #[ink::test]
+        fn points_accrue_across_orders_and_can_be_redeemed() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_points_per_unit(10).unwrap();
+
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            confirm(&mut shop, &first);
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            confirm(&mut shop, &second);
+            assert_eq!(shop.points_of(bob), 240);
+
+            // points are spent in whole discount units, keeping the remainder
+            assert_eq!(shop.redeem_points(150), Ok(1));
+            assert_eq!(shop.points_of(bob), 140);
+
+            let third = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            assert_eq!(third.total_price, 11);
+            assert_eq!(third.loyalty_discount, 1);
+        }
+
+        #[ink::test]
+        fn redeeming_more_points_than_held_fails() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            confirm(&mut shop, &order);
+            assert_eq!(shop.points_of(bob), 12);
+
+            assert_eq!(
+                shop.redeem_points(13),
+                Err(BurgerShopError::InsufficientPoints)
+            );
+            assert_eq!(shop.points_of(bob), 12);
+        }
+
+        #[ink::test]
+        fn batch_orders_share_one_payment() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let before = balance_of(bob);
+            set_caller(bob);
+            set_value(40);
+
+            let orders = shop
+                .take_orders_batch(vec![
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    vec![item(BurgerMenu::ChickenBurger, 1)],
+                ])
+                .unwrap();
+            assert_eq!(orders.len(), 2);
+            assert_eq!(orders[0].paid_amount, 12);
+            assert_eq!(orders[1].paid_amount, 15);
+            assert_ne!(orders[0].order_id, orders[1].order_id);
+            assert_eq!(shop.order_count(), 2);
+
+            // the change above the combined 27 comes back
+            assert_eq!(balance_of(bob), before + 13);
+        }
+
+        #[ink::test]
+        fn underpaid_batch_creates_no_orders() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(26);
+
+            assert_eq!(
+                shop.take_orders_batch(vec![
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    vec![item(BurgerMenu::ChickenBurger, 1)],
+                ])
+                .unwrap_err(),
+                BurgerShopError::IncorrectPayment {
+                    expected: 27,
+                    received: 26,
+                }
+            );
+            // the error reverts the created orders on-chain, see the e2e tests
+        }
+
+        #[ink::test]
+        fn ownership_moves_to_the_new_owner() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+
+            assert_eq!(shop.transfer_ownership(bob), Ok(()));
+            assert_eq!(shop.get_config().owner, bob);
+
+            // the old owner can no longer run the shop
+            assert_eq!(shop.pause(), Err(BurgerShopError::NotOwner));
+            assert_eq!(
+                shop.set_price(BurgerMenu::CheeseBurger, 20),
+                Err(BurgerShopError::NotOwner)
+            );
+
+            set_caller(bob);
+            assert_eq!(shop.pause(), Ok(()));
+        }
+
+        #[ink::test]
+        fn ownership_cannot_go_to_the_zero_address() {
+            let mut shop = shop();
+
+            assert_eq!(
+                shop.transfer_ownership(AccountId::from([0u8; 32])),
+                Err(BurgerShopError::ZeroAddress)
+            );
+            assert_eq!(shop.get_config().owner, accounts().alice);
+        }
+
+        #[ink::test]
+        fn paused_shop_takes_no_orders_until_unpaused() {
+            let mut shop = shop();
+            shop.pause().unwrap();
+            set_caller(accounts().bob);
+            set_value(12);
+
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::ShopPaused
+            );
+
+            set_caller(accounts().alice);
+            shop.unpause().unwrap();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+            assert!(order.paid);
+        }
+
+        #[ink::test]
+        fn coupon_orders_hand_back_change_above_the_discounted_total() {
+            let mut shop = shop();
+            shop.create_coupon(b"HALF".to_vec(), 50).unwrap();
+            let bob = accounts().bob;
+            let before = balance_of(bob);
+            set_caller(bob);
+            set_value(15);
+
+            let order = shop
+                .take_order_and_payment_with_coupon(
+                    vec![item(BurgerMenu::CheeseBurger, 2)],
+                    Some(b"HALF".to_vec()),
+                )
+                .unwrap();
+            assert_eq!(order.total_price, 12);
+            assert_eq!(order.paid_amount, 12);
+            assert_eq!(balance_of(bob), before + 3);
+        }
+
+        #[ink::test]
+        fn estimate_matches_the_amount_charged() {
+            let mut shop = shop();
+            shop.set_tax_basis_points(1_000).unwrap();
+            shop.set_container_deposit(2).unwrap();
+            let items = vec![
+                item(BurgerMenu::CheeseBurger, 2),
+                item(BurgerMenu::VeggieBurger, 1),
+            ];
+            let bob = accounts().bob;
+            set_caller(bob);
+            let estimate = shop.estimate_order_price(items.clone()).unwrap();
+
+            // overpay so any gap between estimate and charge shows in the change
+            let before = balance_of(bob);
+            set_value(estimate + 5);
+            let order = shop.take_order_and_payment(items).unwrap();
+            assert_eq!(order.paid_amount, estimate);
+            assert_eq!(balance_of(bob), before + 5);
+        }
+
+        #[ink::test]
+        fn orders_are_capped_at_the_item_limit() {
+            let mut shop = shop();
+            shop.set_max_items_per_order(3).unwrap();
+
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![
+                    item(BurgerMenu::CheeseBurger, 2),
+                    item(BurgerMenu::ChickenBurger, 1),
+                ],
+            );
+            assert!(order.paid);
+
+            set_value(48);
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 4)])
+                    .unwrap_err(),
+                BurgerShopError::OrderTooLarge
+            );
+
+            // the owner can raise the limit
+            set_caller(accounts().alice);
+            shop.set_max_items_per_order(4).unwrap();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 4)],
+            );
+            assert!(order.paid);
+        }
+
+        #[ink::test]
+        fn only_the_owner_can_set_the_item_limit() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+
+            assert_eq!(
+                shop.set_max_items_per_order(1_000),
+                Err(BurgerShopError::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn notes_are_stored_with_the_order() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(12);
+
+            let order = shop
+                .take_order_and_payment_with_note(
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    b"no pickles".to_vec(),
+                )
+                .unwrap();
+            assert_eq!(
+                shop.get_single_order(order.order_id).unwrap().note,
+                b"no pickles".to_vec()
+            );
+        }
+
+        #[ink::test]
+        fn notes_over_the_length_limit_are_rejected() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(12);
+
+            let order = shop.take_order_and_payment_with_note(
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+                vec![b'x'; MAX_NOTE_LENGTH],
+            );
+            assert!(order.is_ok());
+            assert_eq!(
+                shop.take_order_and_payment_with_note(
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    vec![b'x'; MAX_NOTE_LENGTH + 1],
+                )
+                .unwrap_err(),
+                BurgerShopError::NoteTooLong
+            );
+        }
+
+        #[ink::test]
+        fn tips_count_towards_revenue_and_the_tip_total() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(15);
+
+            let order = shop
+                .take_order_and_payment_with_tip(vec![item(BurgerMenu::CheeseBurger, 1)], 3)
+                .unwrap();
+            assert_eq!(order.tip, 3);
+            assert_eq!(order.paid_amount, 15);
+            confirm(&mut shop, &order);
+
+            set_caller(accounts().alice);
+            assert_eq!(shop.get_total_revenue(), Ok(15));
+            assert_eq!(shop.get_total_tips(), Ok(3));
+        }
+
+        #[ink::test]
+        fn tips_must_be_paid_on_top_of_the_total() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(12);
+
+            assert_eq!(
+                shop.take_order_and_payment_with_tip(vec![item(BurgerMenu::CheeseBurger, 1)], 3)
+                    .unwrap_err(),
+                BurgerShopError::IncorrectPayment {
+                    expected: 15,
+                    received: 12,
+                }
+            );
+        }
+
+        fn ids(orders: Vec<(u32, Order)>) -> Vec<u32> {
+            orders.into_iter().map(|(id, _)| id).collect()
+        }
+
+        #[ink::test]
+        fn orders_are_filtered_by_status() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let paid = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            let preparing = place(&mut shop, bob, vec![item(BurgerMenu::ChickenBurger, 1)]);
+            let ready = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 1)]);
+
+            set_caller(accounts().alice);
+            shop.advance_order_status(preparing.order_id).unwrap();
+            shop.advance_order_status(ready.order_id).unwrap();
+            shop.advance_order_status(ready.order_id).unwrap();
+
+            assert_eq!(
+                ids(shop.get_orders_by_status(OrderStatus::Paid)),
+                vec![paid.order_id]
+            );
+            assert_eq!(
+                ids(shop.get_orders_by_status(OrderStatus::Preparing)),
+                vec![preparing.order_id]
+            );
+            assert_eq!(
+                ids(shop.get_orders_by_status(OrderStatus::Ready)),
+                vec![ready.order_id]
+            );
+            assert!(shop.get_orders_by_status(OrderStatus::Delivered).is_empty());
+        }
+
+        #[ink::test]
+        fn updating_a_reservation_reprices_it() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(5);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            assert_eq!(order.total_price, 24);
+
+            let order = shop
+                .update_order_items(
+                    order.order_id,
+                    vec![
+                        item(BurgerMenu::ChickenBurger, 1),
+                        item(BurgerMenu::VeggieBurger, 1),
+                    ],
+                )
+                .unwrap();
+            assert_eq!(order.total_price, 25);
+            assert_eq!(order.paid_amount, 5);
+            assert_eq!(
+                shop.get_single_order(order.order_id).unwrap().total_price,
+                25
+            );
+
+            // the balance owed follows the new total
+            set_value(20);
+            assert!(shop.pay_balance(order.order_id).unwrap().paid);
+        }
+
+        #[ink::test]
+        fn updating_a_reservation_keeps_redeemed_points() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_points_per_unit(100).unwrap();
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 1)]);
+            confirm(&mut shop, &first);
+            assert_eq!(shop.redeem_points(200), Ok(2));
+
+            set_value(5);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            assert_eq!(order.total_price, 22);
+
+            let order = shop
+                .update_order_items(order.order_id, vec![item(BurgerMenu::ChickenBurger, 1)])
+                .unwrap();
+            assert_eq!(order.total_price, 13);
+            assert_eq!(order.loyalty_discount, 2);
+        }
+
+        #[ink::test]
+        fn updating_a_reservation_ignores_its_own_surge() {
+            let mut shop = shop();
+            shop.set_surge_pricing(1_000, 5_000).unwrap();
+            set_caller(accounts().bob);
+            set_value(5);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::VeggieBurger, 10)])
+                .unwrap();
+            assert_eq!(order.total_price, 100);
+
+            // the same items cost the same, with nothing else pending
+            let order = shop
+                .update_order_items(order.order_id, vec![item(BurgerMenu::VeggieBurger, 10)])
+                .unwrap();
+            assert_eq!(order.total_price, 100);
+        }
+
+        #[ink::test]
+        fn split_orders_cannot_be_updated() {
+            let mut shop = shop();
+            let accounts = accounts();
+            set_caller(accounts.bob);
+            let order = shop
+                .take_order_split_payment(
+                    vec![item(BurgerMenu::CheeseBurger, 2)],
+                    vec![(accounts.bob, 12), (accounts.charlie, 12)],
+                )
+                .unwrap();
+
+            assert_eq!(
+                shop.update_order_items(order.order_id, vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::SplitOrder
+            );
+            assert_eq!(
+                shop.get_single_order(order.order_id).unwrap().total_price,
+                24
+            );
+        }
+
+        #[ink::test]
+        fn paid_orders_cannot_be_updated() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+
+            assert_eq!(
+                shop.update_order_items(order.order_id, vec![item(BurgerMenu::CheeseBurger, 2)])
+                    .unwrap_err(),
+                BurgerShopError::AlreadyPaid
+            );
+        }
+
+        #[ink::test]
+        fn cancelling_emits_order_removed() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 1)]);
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            shop.cancel_order(order.order_id).unwrap();
+
+            let removed: Vec<OrderRemoved> = events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::OrderRemoved(removed) => Some(removed),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(removed.len(), 1);
+            assert_eq!(removed[0].order_id, order.order_id);
+            assert_eq!(removed[0].customer, bob);
+        }
+
+        #[ink::test]
+        fn custom_menus_set_prices_and_stock() {
+            test::set_callee::<DefaultEnvironment>(AccountId::from(SHOP));
+            let shop = BurgerShop::new_with_menu(vec![
+                (BurgerMenu::CheeseBurger, 9, 20),
+                (BurgerMenu::ChickenBurger, 14, 5),
+            ]);
+
+            assert_eq!(
+                shop.get_menu(),
+                vec![
+                    (BurgerMenu::CheeseBurger, 9),
+                    (BurgerMenu::ChickenBurger, 14),
+                    (BurgerMenu::VeggieBurger, 10),
+                ]
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(20))
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::ChickenBurger),
+                Ok(Some(5))
+            );
+            // burgers left off the menu keep their default price, untracked
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::VeggieBurger),
+                Ok(None)
+            );
+        }
+
+        #[ink::test]
+        fn cooks_advance_orders_but_cannot_withdraw() {
+            let mut shop = shop();
+            let accounts = accounts();
+            shop.grant_role(accounts.charlie, Role::Cook).unwrap();
+            let order = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+
+            set_caller(accounts.charlie);
+            assert_eq!(
+                shop.advance_order_status(order.order_id),
+                Ok(OrderStatus::Preparing)
+            );
+            assert_eq!(shop.withdraw(1), Err(BurgerShopError::NotOwner));
+
+            // customers can't work the kitchen
+            set_caller(accounts.bob);
+            assert_eq!(
+                shop.advance_order_status(order.order_id),
+                Err(BurgerShopError::Unauthorized)
+            );
+        }
+
+        #[ink::test]
+        fn shop_balance_holds_payments_at_the_multiplier() {
+            let shop_account = AccountId::from(SHOP);
+            test::set_callee::<DefaultEnvironment>(shop_account);
+            test::set_account_balance::<DefaultEnvironment>(shop_account, 0);
+            let mut shop = BurgerShop::new_with_multiplier(1_000).unwrap();
+            assert_eq!(shop.shop_balance(), 0);
+
+            let bob = accounts().bob;
+            test::set_account_balance::<DefaultEnvironment>(bob, 100_000);
+            set_caller(bob);
+            test::transfer_in::<DefaultEnvironment>(27_000);
+            shop.take_orders_batch(vec![
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+                vec![item(BurgerMenu::ChickenBurger, 1)],
+            ])
+            .unwrap();
+
+            assert_eq!(shop.shop_balance(), 27_000);
+            assert_eq!(balance_of(bob), 73_000);
+        }
+
+        #[ink::test]
+        fn partial_refunds_pay_back_part_of_the_order() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            let before = balance_of(bob);
+
+            set_caller(accounts().alice);
+            assert_eq!(shop.partial_refund(order.order_id, 5), Ok(()));
+            assert_eq!(balance_of(bob), before + 5);
+            let order = shop.get_single_order(order.order_id).unwrap();
+            assert_eq!(order.total_price, 19);
+            assert_eq!(order.paid_amount, 19);
+            assert_eq!(shop.escrowed_balance(), 19);
+
+            // only what's left counts as revenue once delivered
+            confirm(&mut shop, &order);
+            set_caller(accounts().alice);
+            assert_eq!(shop.get_total_revenue(), Ok(19));
+        }
+
+        #[ink::test]
+        fn partial_refunds_cannot_exceed_the_total() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 2)],
+            );
+
+            set_caller(accounts().alice);
+            assert_eq!(
+                shop.partial_refund(order.order_id, 25),
+                Err(BurgerShopError::RefundExceedsTotal)
+            );
+            assert_eq!(
+                shop.get_single_order(order.order_id).unwrap().total_price,
+                24
+            );
+        }
+
+        #[ink::test]
+        fn menu_item_names_are_stored_padded() {
+            let mut shop = shop();
+
+            let id = shop.add_menu_item(b"Truffle Burger".to_vec(), 20).unwrap();
+            let name = shop.menu_items.get(id).unwrap().name;
+            assert_eq!(&name.0[..14], b"Truffle Burger");
+            assert!(name.0[14..].iter().all(|byte| *byte == 0));
+
+            // a name filling the whole buffer still fits
+            assert!(shop.add_menu_item(vec![b'x'; MAX_NAME_LENGTH], 20).is_ok());
+        }
+
+        #[ink::test]
+        fn over_long_names_are_rejected() {
+            let mut shop = shop();
+
+            assert_eq!(
+                shop.add_menu_item(vec![b'x'; MAX_NAME_LENGTH + 1], 20),
+                Err(BurgerShopError::NameTooLong)
+            );
+            assert_eq!(
+                shop.add_outlet(vec![b'x'; MAX_NAME_LENGTH + 1]),
+                Err(BurgerShopError::NameTooLong)
+            );
+            assert_eq!(shop.get_menu().len(), BurgerMenu::variants().len());
+        }
+
+        #[ink::test]
+        fn receipt_lines_add_up_to_the_total() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![
+                    item(BurgerMenu::CheeseBurger, 2),
+                    item(BurgerMenu::ChickenBurger, 1),
+                    item(BurgerMenu::VeggieBurger, 1),
+                ],
+            );
+
+            assert_eq!(
+                shop.get_order_receipt(order.order_id),
+                Ok(vec![
+                    (BurgerMenu::CheeseBurger, 2, 24),
+                    (BurgerMenu::ChickenBurger, 1, 15),
+                    (BurgerMenu::VeggieBurger, 1, 10),
+                ])
+            );
+        }
+
+        #[ink::test]
+        fn discounted_receipts_keep_their_prices() {
+            let mut shop = shop();
+            shop.create_coupon(b"SAVE30".to_vec(), 30).unwrap();
+            set_caller(accounts().bob);
+            set_value(49);
+            let order = shop
+                .take_order_and_payment_with_coupon(
+                    vec![
+                        item(BurgerMenu::CheeseBurger, 2),
+                        item(BurgerMenu::ChickenBurger, 1),
+                        item(BurgerMenu::VeggieBurger, 1),
+                    ],
+                    Some(b"SAVE30".to_vec()),
+                )
+                .unwrap();
+            set_caller(accounts().alice);
+            shop.set_price(BurgerMenu::CheeseBurger, 50).unwrap();
+
+            let receipt = shop.get_order_receipt(order.order_id).unwrap();
+            let total: Balance = receipt.iter().map(|(_, _, total)| total).sum();
+            assert_eq!(total, order.total_price);
+            assert!(receipt[0].2 < 24);
+        }
+
+        #[ink::test]
+        fn paused_shops_can_be_drained() {
+            let mut shop = shop();
+            let django = accounts().django;
+            shop.pause().unwrap();
+
+            assert_eq!(shop.emergency_withdraw(django), Ok(()));
+            assert_eq!(shop.shop_balance(), 0);
+            assert_eq!(balance_of(django), SHOP_FUNDS);
+        }
+
+        #[ink::test]
+        fn draining_leaves_escrowed_payments_behind() {
+            let mut shop = shop();
+            let accounts = accounts();
+            let order = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::CheeseBurger, 2)],
+            );
+            set_caller(accounts.alice);
+            shop.pause().unwrap();
+
+            assert_eq!(shop.emergency_withdraw(accounts.django), Ok(()));
+            assert_eq!(balance_of(accounts.django), SHOP_FUNDS - 24);
+            assert_eq!(shop.shop_balance(), 24);
+
+            // the customer can still get their money back
+            let before = balance_of(accounts.bob);
+            shop.unpause().unwrap();
+            set_caller(accounts.bob);
+            shop.cancel_order(order.order_id).unwrap();
+            assert_eq!(balance_of(accounts.bob), before + 24);
+        }
+
+        #[ink::test]
+        fn active_shops_cannot_be_drained() {
+            let mut shop = shop();
+
+            assert_eq!(
+                shop.emergency_withdraw(accounts().django),
+                Err(BurgerShopError::NotPaused)
+            );
+            assert_eq!(shop.shop_balance(), SHOP_FUNDS);
+        }
+
+        #[ink::test]
+        fn tax_is_charged_at_the_rate_when_ordered() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_tax_basis_points(1_000).unwrap();
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 5)]);
+            assert_eq!(first.tax, 5);
+            assert_eq!(first.paid_amount, 55);
+
+            set_caller(accounts().alice);
+            shop.set_tax_basis_points(2_000).unwrap();
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 5)]);
+            assert_eq!(second.tax, 10);
+            assert_eq!(shop.get_single_order(first.order_id).unwrap().tax, 5);
+
+            confirm(&mut shop, &first);
+            confirm(&mut shop, &second);
+            set_caller(accounts().alice);
+            assert_eq!(shop.get_total_tax_collected(), Ok(15));
+        }
+
+        #[ink::test]
+        fn orders_are_filtered_by_creation_time() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let mut placed = Vec::new();
+            for timestamp in [1_000, 2_000, 3_000, 4_000] {
+                set_time(timestamp);
+                placed
+                    .push(place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]).order_id);
+            }
+
+            // both ends of the window are included
+            assert_eq!(
+                shop.get_orders_between(2_000, 3_000).map(ids),
+                Ok(vec![placed[1], placed[2]])
+            );
+            assert_eq!(
+                shop.get_orders_between(2_500, 2_600).map(ids),
+                Ok(Vec::new())
+            );
+            assert_eq!(
+                shop.get_orders_between(3_000, 2_000).map(ids),
+                Err(BurgerShopError::InvalidRange)
+            );
+        }
+
+        #[ink::test]
+        fn expired_reservations_are_swept() {
+            let mut shop = shop();
+            shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 5)
+                .unwrap();
+            let bob = accounts().bob;
+            set_time(1_000);
+            set_caller(bob);
+            set_value(5);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(3))
+            );
+
+            // nothing to sweep until the reservation runs out
+            set_time(1_000 + DEFAULT_RESERVATION_TTL);
+            assert_eq!(shop.expire_reservations(), Ok(0));
+
+            let before = balance_of(bob);
+            set_time(1_000 + DEFAULT_RESERVATION_TTL + 1);
+            assert_eq!(shop.expire_reservations(), Ok(1));
+            assert_eq!(
+                shop.get_single_order(order.order_id).unwrap_err(),
+                BurgerShopError::OrderNotFound
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(5))
+            );
+            assert_eq!(balance_of(bob), before + 5);
+        }
+
+        #[ink::test]
+        fn expired_reservations_refund_each_payer() {
+            let mut shop = shop();
+            let accounts = accounts();
+            set_time(1_000);
+            set_caller(accounts.bob);
+            set_value(5);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+
+            // charlie chips in towards the balance
+            set_caller(accounts.charlie);
+            set_value(10);
+            shop.pay_balance(order.order_id).unwrap();
+            set_value(0);
+
+            let bob_before = balance_of(accounts.bob);
+            let charlie_before = balance_of(accounts.charlie);
+            set_time(1_000 + DEFAULT_RESERVATION_TTL + 1);
+            assert_eq!(shop.expire_reservations(), Ok(1));
+            assert_eq!(balance_of(accounts.bob), bob_before + 5);
+            assert_eq!(balance_of(accounts.charlie), charlie_before + 10);
+            assert!(shop.split_payers.get(order.order_id).is_none());
+        }
+
+        #[ink::test]
+        fn encoded_orders_decode_back() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(
+                &mut shop,
+                bob,
+                vec![
+                    item(BurgerMenu::CheeseBurger, 2),
+                    item(BurgerMenu::VeggieBurger, 1),
+                ],
+            );
+
+            let bytes = shop.encode_order(order.order_id).unwrap();
+            let decoded = <Order as scale::Decode>::decode(&mut &bytes[..]).unwrap();
+            assert_eq!(decoded.order_id, order.order_id);
+            assert_eq!(decoded.customer, bob);
+            assert_eq!(decoded.total_price, 34);
+            assert_eq!(decoded.list_of_items.len(), 2);
+            assert_eq!(decoded.encode(), bytes);
+
+            assert_eq!(
+                shop.encode_order(order.order_id + 1),
+                Err(BurgerShopError::OrderNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn orders_must_reach_the_minimum_value() {
+            let mut shop = shop();
+            shop.set_min_order_value(24).unwrap();
+            set_caller(accounts().bob);
+            set_value(15);
+
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::ChickenBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::BelowMinimum
+            );
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 2)],
+            );
+            assert_eq!(order.total_price, 24);
+
+            // lowering the minimum lets the small order through
+            set_caller(accounts().alice);
+            shop.set_min_order_value(15).unwrap();
+            assert!(
+                place(
+                    &mut shop,
+                    accounts().bob,
+                    vec![item(BurgerMenu::ChickenBurger, 1)]
+                )
+                .paid
+            );
+
+            set_caller(accounts().bob);
+            assert_eq!(shop.set_min_order_value(0), Err(BurgerShopError::NotOwner));
+        }
+
+        #[ink::test]
+        fn customer_spend_adds_up_orders_net_of_refunds() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            confirm(&mut shop, &first);
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::ChickenBurger, 1)]);
+            confirm(&mut shop, &second);
+            assert_eq!(shop.customer_spend(bob), 39);
+
+            set_caller(accounts().alice);
+            shop.partial_refund(first.order_id, 5).unwrap();
+            assert_eq!(shop.customer_spend(bob), 34);
+            assert_eq!(shop.customer_spend(accounts().charlie), 0);
+        }
+
+        #[ink::test]
+        fn only_the_owner_can_upgrade_the_code() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+
+            assert_eq!(
+                shop.set_code(ink::primitives::Hash::from([0x42; 32])),
+                Err(BurgerShopError::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn restock_batch_tops_up_every_burger() {
+            let mut shop = shop();
+            shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 2)
+                .unwrap();
+
+            assert_eq!(
+                shop.restock_batch(
+                    MAIN_OUTLET,
+                    vec![
+                        (BurgerMenu::CheeseBurger, 3),
+                        (BurgerMenu::ChickenBurger, 4),
+                        (BurgerMenu::VeggieBurger, 5),
+                    ],
+                ),
+                Ok(())
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(5))
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::ChickenBurger),
+                Ok(Some(4))
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::VeggieBurger),
+                Ok(Some(5))
+            );
+        }
+
+        #[ink::test]
+        fn restock_batch_reports_overflow() {
+            let mut shop = shop();
+            shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, u32::MAX)
+                .unwrap();
+
+            assert_eq!(
+                shop.restock_batch(MAIN_OUTLET, vec![(BurgerMenu::CheeseBurger, 1)]),
+                Err(BurgerShopError::Overflow)
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(u32::MAX))
+            );
+        }
+
+        #[ink::test]
+        fn staff_can_restock_but_customers_cannot() {
+            let mut shop = shop();
+            let accounts = accounts();
+            shop.grant_role(accounts.charlie, Role::Cook).unwrap();
+
+            set_caller(accounts.charlie);
+            assert_eq!(
+                shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 2),
+                Ok(())
+            );
+            assert_eq!(
+                shop.restock_batch(MAIN_OUTLET, vec![(BurgerMenu::CheeseBurger, 3)]),
+                Ok(())
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(5))
+            );
+
+            set_caller(accounts.bob);
+            assert_eq!(
+                shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 1),
+                Err(BurgerShopError::Unauthorized)
+            );
+            assert_eq!(
+                shop.restock_batch(MAIN_OUTLET, vec![(BurgerMenu::CheeseBurger, 1)]),
+                Err(BurgerShopError::Unauthorized)
+            );
+        }
+
+        #[ink::test]
+        fn low_stock_includes_levels_at_the_threshold() {
+            let mut shop = shop();
+            shop.restock_batch(
+                MAIN_OUTLET,
+                vec![
+                    (BurgerMenu::CheeseBurger, 2),
+                    (BurgerMenu::ChickenBurger, 3),
+                    (BurgerMenu::VeggieBurger, 4),
+                ],
+            )
+            .unwrap();
+
+            assert_eq!(
+                shop.low_stock(MAIN_OUTLET, 3),
+                Ok(vec![
+                    (BurgerMenu::CheeseBurger, 2),
+                    (BurgerMenu::ChickenBurger, 3),
+                ])
+            );
+            assert_eq!(shop.low_stock(MAIN_OUTLET, 1), Ok(Vec::new()));
+        }
+
+        #[ink::test]
+        fn happy_hour_discounts_only_orders_inside_the_window() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_happy_hour(17 * 3_600, 18 * 3_600, 50).unwrap();
+
+            set_time(17 * 3_600 * 1_000);
+            set_caller(bob);
+            assert_eq!(
+                shop.estimate_order_price(vec![item(BurgerMenu::CheeseBurger, 2)]),
+                Ok(12)
+            );
+            set_value(12);
+            let inside = shop
+                .take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            assert_eq!(inside.total_price, 12);
+            assert_eq!(inside.paid_amount, 12);
+
+            // the window ends at 18:00
+            set_time(18 * 3_600 * 1_000);
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 2)])
+                    .unwrap_err(),
+                BurgerShopError::IncorrectPayment {
+                    expected: 24,
+                    received: 12,
+                }
+            );
+            let outside = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            assert_eq!(outside.total_price, 24);
+        }
+
+        #[ink::test]
+        fn container_deposits_are_returned_once() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_container_deposit(3).unwrap();
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            assert_eq!(order.paid_amount, 15);
+
+            // the payment must leave escrow first
+            set_caller(accounts().alice);
+            assert_eq!(
+                shop.return_container(order.order_id),
+                Err(BurgerShopError::StillEscrowed)
+            );
+            confirm(&mut shop, &order);
+
+            let before = balance_of(bob);
+            set_caller(accounts().alice);
+            assert_eq!(shop.return_container(order.order_id), Ok(()));
+            assert_eq!(balance_of(bob), before + 3);
+            assert_eq!(shop.get_total_revenue(), Ok(12));
+
+            assert_eq!(
+                shop.return_container(order.order_id),
+                Err(BurgerShopError::DepositAlreadyReturned)
+            );
+            assert_eq!(balance_of(bob), before + 3);
+        }
+
+        #[ink::test]
+        fn allowlist_limits_orders_only_while_enabled() {
+            let mut shop = shop();
+            let accounts = accounts();
+            shop.allow(accounts.bob).unwrap();
+            shop.set_allowlist_enabled(true).unwrap();
+
+            assert!(
+                place(
+                    &mut shop,
+                    accounts.bob,
+                    vec![item(BurgerMenu::CheeseBurger, 1)]
+                )
+                .paid
+            );
+            set_caller(accounts.charlie);
+            set_value(12);
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::NotAllowlisted
+            );
+
+            // switching the allowlist off opens the shop to everyone
+            set_caller(accounts.alice);
+            shop.set_allowlist_enabled(false).unwrap();
+            assert!(
+                place(
+                    &mut shop,
+                    accounts.charlie,
+                    vec![item(BurgerMenu::CheeseBurger, 1)]
+                )
+                .paid
+            );
+
+            // and removed accounts are shut out once it's back on
+            set_caller(accounts.alice);
+            shop.disallow(accounts.bob).unwrap();
+            shop.set_allowlist_enabled(true).unwrap();
+            set_caller(accounts.bob);
+            set_value(12);
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::NotAllowlisted
+            );
+        }
+
+        #[ink::test]
+        fn daily_order_cap_resets_the_next_day() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_max_orders_per_day(2).unwrap();
+            set_time(1_000);
+
+            place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            set_value(12);
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::DailyLimitReached
+            );
+
+            // other customers have their own cap
+            assert!(
+                place(
+                    &mut shop,
+                    accounts().charlie,
+                    vec![item(BurgerMenu::CheeseBurger, 1)]
+                )
+                .paid
+            );
+
+            set_time(MILLIS_PER_DAY);
+            assert!(place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]).paid);
+        }
+
+        #[ink::test]
+        fn status_query_follows_each_transition() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(5);
+            let id = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 1)])
+                .unwrap()
+                .order_id;
+            assert_eq!(shop.get_order_status(id), Ok(OrderStatus::Reserved));
+
+            set_value(7);
+            shop.pay_balance(id).unwrap();
+            assert_eq!(shop.get_order_status(id), Ok(OrderStatus::Paid));
+
+            set_caller(accounts().alice);
+            for status in [
+                OrderStatus::Preparing,
+                OrderStatus::Ready,
+                OrderStatus::Delivered,
+            ] {
+                shop.advance_order_status(id).unwrap();
+                assert_eq!(shop.get_order_status(id), Ok(status));
+            }
+            assert_eq!(
+                shop.get_order_status(id + 1),
+                Err(BurgerShopError::OrderNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn combos_are_charged_their_fixed_price() {
+            let mut shop = shop();
+            shop.restock_batch(
+                MAIN_OUTLET,
+                vec![(BurgerMenu::CheeseBurger, 5), (BurgerMenu::VeggieBurger, 5)],
+            )
+            .unwrap();
+            let combo = shop
+                .add_combo(
+                    vec![
+                        item(BurgerMenu::CheeseBurger, 1),
+                        item(BurgerMenu::VeggieBurger, 1),
+                    ],
+                    18,
+                )
+                .unwrap();
+
+            set_caller(accounts().bob);
+            set_value(18);
+            let order = shop.take_combo_order(combo).unwrap();
+            assert_eq!(order.total_price, 18);
+            assert_eq!(order.list_of_items.len(), 2);
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(4))
+            );
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::VeggieBurger),
+                Ok(Some(4))
+            );
+        }
+
+        #[ink::test]
+        fn unknown_combos_are_rejected() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(18);
+
+            assert_eq!(
+                shop.take_combo_order(7).unwrap_err(),
+                BurgerShopError::UnknownCombo
+            );
+        }
+
+        #[ink::test]
+        fn service_fee_is_paid_on_delivery() {
+            let mut shop = shop();
+            let accounts = accounts();
+            shop.set_service_fee(accounts.django, 1_000).unwrap();
+
+            let order = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::VeggieBurger, 10)],
+            );
+            assert_eq!(order.service_fee, 0);
+            assert_eq!(balance_of(accounts.django), 0);
+            assert_eq!(shop.escrowed_balance(), 100);
+
+            confirm(&mut shop, &order);
+            let order = shop.get_single_order(order.order_id).unwrap();
+            assert_eq!(order.service_fee, 10);
+            assert_eq!(balance_of(accounts.django), 10);
+            assert_eq!(shop.escrowed_balance(), 0);
+        }
+
+        #[ink::test]
+        fn service_fee_is_only_taken_on_the_food() {
+            let mut shop = shop();
+            let accounts = accounts();
+            shop.set_service_fee(accounts.django, 1_000).unwrap();
+            shop.set_container_deposit(5).unwrap();
+            shop.set_tax_basis_points(1_000).unwrap();
+
+            set_caller(accounts.bob);
+            set_value(126);
+            let order = shop
+                .take_order_and_payment_with_tip(vec![item(BurgerMenu::VeggieBurger, 10)], 11)
+                .unwrap();
+            assert_eq!(order.paid_amount, 126);
+
+            confirm(&mut shop, &order);
+            assert_eq!(balance_of(accounts.django), 10);
+        }
+
+        #[ink::test]
+        fn free_cancellation_refunds_the_service_fee_too() {
+            let mut shop = shop();
+            let accounts = accounts();
+            shop.set_service_fee(accounts.django, 1_000).unwrap();
+            shop.set_cancellation_fee(1_000).unwrap();
+
+            let order = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::VeggieBurger, 10)],
+            );
+            set_caller(accounts.alice);
+            shop.advance_order_status(order.order_id).unwrap();
+
+            // still inside the grace period, so nothing is kept back
+            let before = balance_of(accounts.bob);
+            set_caller(accounts.bob);
+            shop.cancel_order(order.order_id).unwrap();
+            assert_eq!(balance_of(accounts.bob), before + 100);
+            assert_eq!(balance_of(accounts.django), 0);
+            assert_eq!(shop.escrowed_balance(), 0);
+        }
+
+        #[ink::test]
+        fn batch_orders_pay_the_service_fee() {
+            let mut shop = shop();
+            let accounts = accounts();
+            shop.set_service_fee(accounts.django, 1_000).unwrap();
+            set_caller(accounts.bob);
+            set_value(200);
+
+            let orders = shop
+                .take_orders_batch(vec![
+                    vec![item(BurgerMenu::VeggieBurger, 10)],
+                    vec![item(BurgerMenu::VeggieBurger, 10)],
+                ])
+                .unwrap();
+            for order in &orders {
+                confirm(&mut shop, order);
+            }
+            assert_eq!(balance_of(accounts.django), 20);
+        }
+
+        #[ink::test]
+        fn retried_orders_return_the_original() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            set_caller(bob);
+            set_value(12);
+            let first = shop
+                .take_order_and_payment_with_ref(
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    b"tx-1".to_vec(),
+                )
+                .unwrap();
+
+            // the retry's payment goes straight back
+            let before = balance_of(bob);
+            let retry = shop
+                .take_order_and_payment_with_ref(
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    b"tx-1".to_vec(),
+                )
+                .unwrap();
+            assert_eq!(retry.order_id, first.order_id);
+            assert_eq!(shop.order_count(), 1);
+            assert_eq!(balance_of(bob), before + 12);
+
+            // a new reference is a new order
+            let other = shop
+                .take_order_and_payment_with_ref(
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    b"tx-2".to_vec(),
+                )
+                .unwrap();
+            assert_ne!(other.order_id, first.order_id);
+            assert_eq!(shop.order_count(), 2);
+        }
+
+        #[ink::test]
+        fn customer_order_count_goes_up_per_order() {
+            let mut shop = shop();
+            let accounts = accounts();
+            assert_eq!(shop.customer_order_count(accounts.bob), 0);
+
+            place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+            assert_eq!(shop.customer_order_count(accounts.bob), 1);
+            place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::VeggieBurger, 1)],
+            );
+            place(
+                &mut shop,
+                accounts.charlie,
+                vec![item(BurgerMenu::VeggieBurger, 1)],
+            );
+            assert_eq!(shop.customer_order_count(accounts.bob), 2);
+            assert_eq!(shop.customer_order_count(accounts.charlie), 1);
+            assert_eq!(shop.customer_order_count(accounts.eve), 0);
+        }
+
+        #[ink::test]
+        fn split_orders_are_paid_once_every_half_is_in() {
+            let mut shop = shop();
+            let accounts = accounts();
+            set_caller(accounts.bob);
+            let order = shop
+                .take_order_split_payment(
+                    vec![item(BurgerMenu::CheeseBurger, 2)],
+                    vec![(accounts.bob, 12), (accounts.charlie, 12)],
+                )
+                .unwrap();
+
+            set_value(12);
+            let order = shop.contribute(order.order_id).unwrap();
+            assert!(!order.paid);
+            assert_eq!(order.paid_amount, 12);
+            assert_eq!(order.status, OrderStatus::Reserved);
+
+            // the kitchen waits until the bill is covered
+            set_caller(accounts.alice);
+            assert_eq!(
+                shop.advance_order_status(order.order_id),
+                Err(BurgerShopError::NotFullyPaid)
+            );
+
+            set_caller(accounts.charlie);
+            set_value(12);
+            let order = shop.contribute(order.order_id).unwrap();
+            assert!(order.paid);
+            assert_eq!(order.paid_amount, 24);
+            assert_eq!(order.status, OrderStatus::Paid);
+            assert_eq!(shop.get_order_payer(order.order_id), Ok(accounts.charlie));
+
+            set_caller(accounts.alice);
+            assert_eq!(
+                shop.advance_order_status(order.order_id),
+                Ok(OrderStatus::Preparing)
+            );
+        }
+
+        #[ink::test]
+        fn split_orders_cannot_be_paid_off_as_a_balance() {
+            let mut shop = shop();
+            let accounts = accounts();
+            set_caller(accounts.bob);
+            let order = shop
+                .take_order_split_payment(
+                    vec![item(BurgerMenu::CheeseBurger, 2)],
+                    vec![(accounts.bob, 12), (accounts.charlie, 12)],
+                )
+                .unwrap();
+
+            set_value(24);
+            assert_eq!(
+                shop.pay_balance(order.order_id).unwrap_err(),
+                BurgerShopError::SplitOrder
+            );
+            assert_eq!(
+                shop.get_single_order(order.order_id).unwrap().paid_amount,
+                0
+            );
+        }
+
+        #[ink::test]
+        fn cancelling_a_split_order_refunds_each_payer() {
+            let mut shop = shop();
+            let accounts = accounts();
+            set_caller(accounts.bob);
+            let order = shop
+                .take_order_split_payment(
+                    vec![item(BurgerMenu::CheeseBurger, 2)],
+                    vec![(accounts.bob, 10), (accounts.charlie, 14)],
+                )
+                .unwrap();
+
+            set_caller(accounts.charlie);
+            set_value(14);
+            shop.contribute(order.order_id).unwrap();
+            set_value(0);
+
+            // bob hasn't paid yet, so only charlie gets money back
+            let bob_before = balance_of(accounts.bob);
+            let charlie_before = balance_of(accounts.charlie);
+            set_caller(accounts.bob);
+            shop.cancel_order(order.order_id).unwrap();
+            assert_eq!(balance_of(accounts.bob), bob_before);
+            assert_eq!(balance_of(accounts.charlie), charlie_before + 14);
+            assert!(shop
+                .contributions
+                .get((order.order_id, accounts.charlie))
+                .is_none());
+            assert!(shop.split_payers.get(order.order_id).is_none());
+        }
+
+        #[ink::test]
+        fn reorders_repeat_the_items_under_a_new_id() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let previous = place(
+                &mut shop,
+                bob,
+                vec![
+                    item(BurgerMenu::CheeseBurger, 2),
+                    item(BurgerMenu::VeggieBurger, 1),
+                ],
+            );
+
+            set_caller(bob);
+            set_value(34);
+            let order = shop.reorder(previous.order_id).unwrap();
+            assert_ne!(order.order_id, previous.order_id);
+            assert_eq!(order.customer, bob);
+            assert_eq!(order.list_of_items.len(), 2);
+            assert_eq!(order.list_of_items[0].burger_menu, BurgerMenu::CheeseBurger);
+            assert_eq!(order.list_of_items[0].amount, 2);
+            assert_eq!(order.list_of_items[1].burger_menu, BurgerMenu::VeggieBurger);
+            assert_eq!(order.list_of_items[1].amount, 1);
+            assert_eq!(shop.order_count(), 2);
+        }
+
+        #[ink::test]
+        fn only_the_customer_can_reorder() {
+            let mut shop = shop();
+            let previous = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+
+            set_caller(accounts().charlie);
+            set_value(12);
+            assert_eq!(
+                shop.reorder(previous.order_id).unwrap_err(),
+                BurgerShopError::NotOwnerOfOrder
+            );
+        }
+
+        #[ink::test]
+        fn refunds_are_capped_per_block() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_max_refund_per_block(30).unwrap();
+            let orders: Vec<Order> = (0..3)
+                .map(|_| place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]))
+                .collect();
+
+            assert_eq!(shop.cancel_order(orders[0].order_id), Ok(()));
+            assert_eq!(shop.cancel_order(orders[1].order_id), Ok(()));
+            assert_eq!(
+                shop.cancel_order(orders[2].order_id),
+                Err(BurgerShopError::RefundRateLimited)
+            );
+
+            // the cap starts over in the next block
+            test::advance_block::<DefaultEnvironment>();
+            assert_eq!(shop.cancel_order(orders[2].order_id), Ok(()));
+        }
+
+        #[ink::test]
+        fn average_order_value_counts_recorded_sales_only() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            assert_eq!(shop.average_order_value(), 0);
+
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            confirm(&mut shop, &first);
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            set_value(5);
+            shop.deposit_and_reserve(vec![item(BurgerMenu::ChickenBurger, 1)])
+                .unwrap();
+            set_caller(accounts().alice);
+            shop.comp_order(vec![item(BurgerMenu::VeggieBurger, 1)], bob)
+                .unwrap();
+
+            // escrowed, reserved and comped orders aren't sales yet
+            assert_eq!(shop.average_order_value(), 24);
+
+            confirm(&mut shop, &second);
+            assert_eq!(shop.average_order_value(), 18);
+        }
+
+        #[ink::test]
+        fn preorders_come_due_at_their_pickup_time() {
+            let mut shop = shop();
+            set_time(1_000);
+            set_caller(accounts().bob);
+            set_value(12);
+            let preorder = shop
+                .take_preorder(vec![item(BurgerMenu::CheeseBurger, 1)], 5_000)
+                .unwrap();
+            place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::VeggieBurger, 1)],
+            );
+            assert!(shop.due_preorders().is_empty());
+
+            set_time(4_999);
+            assert!(shop.due_preorders().is_empty());
+            set_time(5_000);
+            assert_eq!(ids(shop.due_preorders()), vec![preorder.order_id]);
+        }
+
+        #[ink::test]
+        fn preorders_must_be_for_a_later_time() {
+            let mut shop = shop();
+            set_time(1_000);
+            set_caller(accounts().bob);
+            set_value(12);
+
+            assert_eq!(
+                shop.take_preorder(vec![item(BurgerMenu::CheeseBurger, 1)], 1_000)
+                    .unwrap_err(),
+                BurgerShopError::InvalidSchedule
+            );
+        }
+
+        fn zero_account() -> AccountId {
+            AccountId::from([0u8; 32])
+        }
+
+        #[ink::test]
+        fn comps_cannot_go_to_the_zero_address() {
+            let mut shop = shop();
+
+            assert_eq!(
+                shop.comp_order(vec![item(BurgerMenu::CheeseBurger, 1)], zero_account())
+                    .unwrap_err(),
+                BurgerShopError::ZeroAddress
+            );
+        }
+
+        #[ink::test]
+        fn refunds_cannot_go_to_the_zero_address() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+
+            assert_eq!(
+                shop.cancel_order_to(order.order_id, zero_account()),
+                Err(BurgerShopError::ZeroAddress)
+            );
+            assert!(shop.get_single_order(order.order_id).is_ok());
+        }
+
+        #[ink::test]
+        fn service_fees_cannot_go_to_the_zero_address() {
+            let mut shop = shop();
+
+            assert_eq!(
+                shop.set_service_fee(zero_account(), 100),
+                Err(BurgerShopError::ZeroAddress)
+            );
+        }
+
+        #[ink::test]
+        fn emergency_withdrawals_cannot_go_to_the_zero_address() {
+            let mut shop = shop();
+            shop.pause().unwrap();
+
+            assert_eq!(
+                shop.emergency_withdraw(zero_account()),
+                Err(BurgerShopError::ZeroAddress)
+            );
+            assert_eq!(shop.shop_balance(), SHOP_FUNDS);
+        }
+
+        #[ink::test]
+        fn top_orders_are_the_most_expensive_first() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            for amount in [2, 5, 1, 4, 3] {
+                place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, amount)]);
+            }
+
+            let prices = |orders: Vec<(u32, Order)>| -> Vec<Balance> {
+                orders
+                    .into_iter()
+                    .map(|(_, order)| order.total_price)
+                    .collect()
+            };
+            assert_eq!(prices(shop.get_top_orders(3)), vec![50, 40, 30]);
+            assert_eq!(ids(shop.get_top_orders(2)), vec![1, 3]);
+            assert_eq!(shop.get_top_orders(10).len(), 5);
+            assert!(shop.get_top_orders(0).is_empty());
+        }
+
+        #[ink::test]
+        fn cancelled_orders_can_be_refunded_elsewhere() {
+            let mut shop = shop();
+            let accounts = accounts();
+            let order = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::CheeseBurger, 2)],
+            );
+            let bob_before = balance_of(accounts.bob);
+
+            assert_eq!(shop.cancel_order_to(order.order_id, accounts.eve), Ok(()));
+            assert_eq!(balance_of(accounts.eve), 24);
+            assert_eq!(balance_of(accounts.bob), bob_before);
+        }
+
+        #[ink::test]
+        fn unavailable_items_cannot_be_ordered_until_back() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_available(BurgerMenu::ChickenBurger, false)
+                .unwrap();
+
+            set_caller(bob);
+            set_value(27);
+            assert_eq!(
+                shop.take_order_and_payment(vec![
+                    item(BurgerMenu::CheeseBurger, 1),
+                    item(BurgerMenu::ChickenBurger, 1),
+                ])
+                .unwrap_err(),
+                BurgerShopError::ItemUnavailable
+            );
+            // the rest of the menu is still served
+            assert!(place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]).paid);
+
+            set_caller(accounts().alice);
+            shop.set_available(BurgerMenu::ChickenBurger, true).unwrap();
+            assert!(place(&mut shop, bob, vec![item(BurgerMenu::ChickenBurger, 1)]).paid);
+        }
+
+        #[ink::test]
+        fn item_revenue_follows_sales_and_cancellations() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let first = place(
+                &mut shop,
+                bob,
+                vec![
+                    item(BurgerMenu::CheeseBurger, 2),
+                    item(BurgerMenu::VeggieBurger, 1),
+                ],
+            );
+            confirm(&mut shop, &first);
+            let second = place(
+                &mut shop,
+                bob,
+                vec![
+                    item(BurgerMenu::ChickenBurger, 1),
+                    item(BurgerMenu::VeggieBurger, 2),
+                ],
+            );
+            confirm(&mut shop, &second);
+
+            assert_eq!(
+                shop.revenue_by_item(MAIN_OUTLET),
+                Ok(vec![
+                    (BurgerMenu::CheeseBurger, 24),
+                    (BurgerMenu::ChickenBurger, 15),
+                    (BurgerMenu::VeggieBurger, 30),
+                ])
+            );
+
+            // cancelling takes the order's share back off each burger
+            set_caller(bob);
+            shop.cancel_order(second.order_id).unwrap();
+            assert_eq!(
+                shop.revenue_by_item(MAIN_OUTLET),
+                Ok(vec![
+                    (BurgerMenu::CheeseBurger, 24),
+                    (BurgerMenu::ChickenBurger, 0),
+                    (BurgerMenu::VeggieBurger, 10),
+                ])
+            );
+        }
+
+        #[ink::test]
+        fn fractional_discounts_follow_the_rounding_mode() {
+            let mut shop = shop();
+            shop.create_coupon(b"TEN".to_vec(), 10).unwrap();
+            shop.create_coupon(b"THIRTY".to_vec(), 30).unwrap();
+            let bob = accounts().bob;
+
+            // 10% of 12 is 1.2 off, and 30% of 49 is 14.7 off
+            for (mode, ten_off, thirty_off) in [
+                (RoundingMode::Floor, 11, 35),
+                (RoundingMode::Ceil, 10, 34),
+                (RoundingMode::Nearest, 11, 34),
+            ] {
+                set_caller(accounts().alice);
+                shop.set_rounding_mode(mode).unwrap();
+                set_caller(bob);
+                set_value(49);
+
+                let order = shop
+                    .take_order_and_payment_with_coupon(
+                        vec![item(BurgerMenu::CheeseBurger, 1)],
+                        Some(b"TEN".to_vec()),
+                    )
+                    .unwrap();
+                assert_eq!(order.total_price, ten_off);
+
+                let order = shop
+                    .take_order_and_payment_with_coupon(
+                        vec![
+                            item(BurgerMenu::CheeseBurger, 2),
+                            item(BurgerMenu::ChickenBurger, 1),
+                            item(BurgerMenu::VeggieBurger, 1),
+                        ],
+                        Some(b"THIRTY".to_vec()),
+                    )
+                    .unwrap();
+                assert_eq!(order.total_price, thirty_off);
+            }
+        }
+
+        #[ink::test]
+        fn order_hash_changes_only_with_the_order() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+            let hash = shop.order_hash(order.order_id).unwrap();
+            assert_eq!(shop.order_hash(order.order_id), Ok(hash));
+
+            set_caller(accounts().alice);
+            shop.advance_order_status(order.order_id).unwrap();
+            assert_ne!(shop.order_hash(order.order_id), Ok(hash));
+            assert_eq!(
+                shop.order_hash(order.order_id + 1),
+                Err(BurgerShopError::OrderNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn outlets_keep_separate_stock_and_revenue() {
+            let mut shop = shop();
+            let outlet = shop.add_outlet(b"Harbour".to_vec()).unwrap();
+            shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 5)
+                .unwrap();
+            shop.restock(outlet, BurgerMenu::CheeseBurger, 3).unwrap();
+
+            set_caller(accounts().bob);
+            set_value(24);
+            let order = shop
+                .take_order_and_payment_at(outlet, vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+            assert_eq!(order.outlet, outlet);
+            confirm(&mut shop, &order);
+
+            set_caller(accounts().alice);
+            assert_eq!(
+                shop.stock_of(MAIN_OUTLET, BurgerMenu::CheeseBurger),
+                Ok(Some(5))
+            );
+            assert_eq!(shop.stock_of(outlet, BurgerMenu::CheeseBurger), Ok(Some(1)));
+            assert_eq!(shop.get_outlet_revenue(MAIN_OUTLET), Ok(0));
+            assert_eq!(shop.get_outlet_revenue(outlet), Ok(24));
+            assert_eq!(
+                shop.get_outlet_revenue(outlet + 1),
+                Err(BurgerShopError::UnknownOutlet)
+            );
+        }
+
+        #[ink::test]
+        fn callers_list_only_their_own_orders() {
+            let mut shop = shop();
+            let accounts = accounts();
+            let bobs = place(
+                &mut shop,
+                accounts.bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+            let charlies = place(
+                &mut shop,
+                accounts.charlie,
+                vec![item(BurgerMenu::VeggieBurger, 1)],
+            );
+
+            set_caller(accounts.bob);
+            assert_eq!(ids(shop.get_my_orders()), vec![bobs.order_id]);
+            set_caller(accounts.charlie);
+            assert_eq!(ids(shop.get_my_orders()), vec![charlies.order_id]);
+            set_caller(accounts.eve);
+            assert!(shop.get_my_orders().is_empty());
+        }
+
+        #[ink::test]
+        fn comped_orders_do_not_count_as_revenue() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let paid = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            confirm(&mut shop, &paid);
+
+            set_caller(accounts().alice);
+            let comped = shop
+                .comp_order(vec![item(BurgerMenu::ChickenBurger, 2)], bob)
+                .unwrap();
+            assert!(comped.paid);
+            assert!(comped.comped);
+            assert_eq!(comped.customer, bob);
+            assert_eq!(shop.get_total_revenue(), Ok(12));
+
+            // only managers can comp orders
+            set_caller(bob);
+            assert_eq!(
+                shop.comp_order(vec![item(BurgerMenu::ChickenBurger, 1)], bob)
+                    .unwrap_err(),
+                BurgerShopError::Unauthorized
+            );
+        }
+
+        #[ink::test]
+        fn orders_are_filtered_by_creation_block() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            test::advance_block::<DefaultEnvironment>();
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            test::advance_block::<DefaultEnvironment>();
+            let third = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+
+            let block = second.created_at_block;
+            assert_eq!(block, first.created_at_block + 1);
+            assert_eq!(
+                ids(shop.get_orders_since_block(block)),
+                vec![second.order_id, third.order_id]
+            );
+            assert_eq!(
+                ids(shop.get_orders_since_block(0)),
+                vec![first.order_id, second.order_id, third.order_id]
+            );
+            assert!(shop.get_orders_since_block(block + 2).is_empty());
+        }
+
+        #[ink::test]
+        fn order_ids_are_not_reused_after_a_cancel() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 1)]);
+            shop.cancel_order(first.order_id).unwrap();
+
+            let third = place(&mut shop, bob, vec![item(BurgerMenu::ChickenBurger, 1)]);
+            assert_ne!(third.order_id, first.order_id);
+            assert_ne!(third.order_id, second.order_id);
+            assert_eq!(
+                shop.get_single_order(second.order_id).unwrap().total_price,
+                10
+            );
+            assert_eq!(
+                shop.get_single_order(third.order_id).unwrap().total_price,
+                15
+            );
+        }
+
+        #[ink::test]
+        fn batch_advance_reports_each_order() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let paid = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            let delivered = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            set_value(5);
+            let reserved = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 1)])
+                .unwrap();
+
+            set_caller(accounts().alice);
+            for _ in 0..3 {
+                shop.advance_order_status(delivered.order_id).unwrap();
+            }
+            assert_eq!(
+                shop.advance_orders_batch(vec![
+                    paid.order_id,
+                    delivered.order_id,
+                    reserved.order_id,
+                    99,
+                ]),
+                Ok(vec![
+                    (paid.order_id, Ok(OrderStatus::Preparing)),
+                    (
+                        delivered.order_id,
+                        Err(BurgerShopError::InvalidStatusTransition)
+                    ),
+                    (reserved.order_id, Err(BurgerShopError::NotFullyPaid)),
+                    (99, Err(BurgerShopError::OrderNotFound)),
+                ])
+            );
+            assert_eq!(
+                shop.get_order_status(paid.order_id),
+                Ok(OrderStatus::Preparing)
+            );
+        }
+
+        #[ink::test]
+        fn late_cancels_keep_the_cancellation_fee() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_cancel_grace_period(60_000).unwrap();
+            shop.set_cancellation_fee(2_000).unwrap();
+            set_time(1_000);
+            let early = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 5)]);
+            let late = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 5)]);
+            let waiting = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 5)]);
+            set_caller(accounts().alice);
+            shop.advance_order_status(early.order_id).unwrap();
+            shop.advance_order_status(late.order_id).unwrap();
+            set_caller(bob);
+
+            // the grace period includes its last millisecond
+            set_time(61_000);
+            let before = balance_of(bob);
+            shop.cancel_order(early.order_id).unwrap();
+            assert_eq!(balance_of(bob), before + 50);
+
+            set_time(61_001);
+            let before = balance_of(bob);
+            shop.cancel_order(late.order_id).unwrap();
+            assert_eq!(balance_of(bob), before + 40);
+
+            // orders the kitchen hasn't started are always free to cancel
+            let before = balance_of(bob);
+            shop.cancel_order(waiting.order_id).unwrap();
+            assert_eq!(balance_of(bob), before + 50);
+        }
+
+        #[ink::test]
+        fn config_reflects_the_setters() {
+            let mut shop = shop();
+            assert_eq!(
+                shop.get_config(),
+                ShopConfig {
+                    price_multiplier: 1,
+                    tax_basis_points: 0,
+                    min_order_value: 0,
+                    max_items_per_order: DEFAULT_MAX_ITEMS_PER_ORDER,
+                    paused: false,
+                    owner: accounts().alice,
+                }
+            );
+
+            shop.set_tax_basis_points(750).unwrap();
+            shop.set_min_order_value(20).unwrap();
+            shop.set_max_items_per_order(8).unwrap();
+            shop.pause().unwrap();
+            shop.transfer_ownership(accounts().bob).unwrap();
+            assert_eq!(
+                shop.get_config(),
+                ShopConfig {
+                    price_multiplier: 1,
+                    tax_basis_points: 750,
+                    min_order_value: 20,
+                    max_items_per_order: 8,
+                    paused: true,
+                    owner: accounts().bob,
+                }
+            );
+        }
+
+        #[ink::test]
+        fn confirming_delivery_releases_escrow_as_revenue() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            assert_eq!(order.escrow, 24);
+            assert_eq!(shop.escrowed_balance(), 24);
+            set_caller(accounts().alice);
+            assert_eq!(shop.get_total_revenue(), Ok(0));
+
+            // only the customer confirms
+            assert_eq!(
+                shop.confirm_delivery(order.order_id),
+                Err(BurgerShopError::NotOwnerOfOrder)
+            );
+            confirm(&mut shop, &order);
+            assert_eq!(shop.escrowed_balance(), 0);
+            set_caller(accounts().alice);
+            assert_eq!(shop.get_total_revenue(), Ok(24));
+
+            set_caller(bob);
+            assert_eq!(
+                shop.confirm_delivery(order.order_id),
+                Err(BurgerShopError::NotEscrowed)
+            );
+        }
+
+        #[ink::test]
+        fn disputes_are_released_or_refunded_by_staff() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let kept = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            let refunded = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 1)]);
+            shop.open_dispute(kept.order_id).unwrap();
+            shop.open_dispute(refunded.order_id).unwrap();
+            assert_eq!(
+                shop.confirm_delivery(kept.order_id),
+                Err(BurgerShopError::OrderDisputed)
+            );
+
+            set_caller(accounts().alice);
+            shop.resolve_dispute(kept.order_id, true).unwrap();
+            assert_eq!(shop.get_total_revenue(), Ok(12));
+
+            let before = balance_of(bob);
+            shop.resolve_dispute(refunded.order_id, false).unwrap();
+            assert_eq!(balance_of(bob), before + 10);
+            assert_eq!(
+                shop.get_single_order(refunded.order_id).unwrap_err(),
+                BurgerShopError::OrderNotFound
+            );
+            assert_eq!(shop.escrowed_balance(), 0);
+            assert_eq!(shop.get_total_revenue(), Ok(12));
+        }
+
+        #[ink::test]
+        fn escrowed_payments_cannot_be_withdrawn() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 2)],
+            );
+            // the contract's balance holds the escrow on top of its funds
+            let balance = shop.shop_balance();
+
+            set_caller(accounts().alice);
+            assert_eq!(shop.withdraw(balance), Err(BurgerShopError::StillEscrowed));
+            assert_eq!(shop.withdraw(balance - 24), Ok(()));
+
+            confirm(&mut shop, &order);
+            set_caller(accounts().alice);
+            assert_eq!(shop.withdraw(24), Ok(()));
+        }
+
+        #[ink::test]
+        fn chain_values_convert_both_ways() {
+            test::set_callee::<DefaultEnvironment>(AccountId::from(SHOP));
+            let shop = BurgerShop::new();
+
+            let value = shop.to_chain_value(12).unwrap();
+            assert_eq!(value, 12 * DEFAULT_PRICE_MULTIPLIER);
+            assert_eq!(shop.from_chain_value(value), 12);
+            // partial units round down
+            assert_eq!(
+                shop.from_chain_value(value + DEFAULT_PRICE_MULTIPLIER - 1),
+                12
+            );
+
+            assert_eq!(
+                shop.to_chain_value(Balance::MAX),
+                Err(BurgerShopError::Overflow)
+            );
+        }
+
+        #[ink::test]
+        fn third_parties_can_pay_for_an_order() {
+            let mut shop = shop();
+            let accounts = accounts();
+            set_caller(accounts.bob);
+            set_value(5);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 1)])
+                .unwrap();
+            assert_eq!(shop.get_order_payer(order.order_id), Ok(accounts.bob));
+
+            set_caller(accounts.charlie);
+            set_value(7);
+            let order = shop.pay_balance(order.order_id).unwrap();
+            assert!(order.paid);
+            assert_eq!(order.customer, accounts.bob);
+            assert_eq!(shop.get_order_payer(order.order_id), Ok(accounts.charlie));
+        }
+
+        #[ink::test]
+        fn cancelling_refunds_a_third_party_payment() {
+            let mut shop = shop();
+            let accounts = accounts();
+            set_caller(accounts.bob);
+            set_value(5);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 2)])
+                .unwrap();
+
+            set_caller(accounts.charlie);
+            set_value(10);
+            shop.pay_balance(order.order_id).unwrap();
+            set_value(0);
+            assert_eq!(shop.get_order_payer(order.order_id), Ok(accounts.charlie));
+
+            let bob_before = balance_of(accounts.bob);
+            let charlie_before = balance_of(accounts.charlie);
+            set_caller(accounts.bob);
+            shop.cancel_order(order.order_id).unwrap();
+            assert_eq!(balance_of(accounts.bob), bob_before + 5);
+            assert_eq!(balance_of(accounts.charlie), charlie_before + 10);
+        }
+
+        #[ink::test]
+        fn zero_amount_items_are_rejected() {
+            let mut shop = shop();
+            set_caller(accounts().bob);
+            set_value(12);
+
+            assert_eq!(
+                shop.take_order_and_payment(vec![
+                    item(BurgerMenu::CheeseBurger, 1),
+                    item(BurgerMenu::VeggieBurger, 0),
+                ])
+                .unwrap_err(),
+                BurgerShopError::ZeroAmountItem
+            );
+            assert_eq!(
+                shop.estimate_order_price(vec![item(BurgerMenu::VeggieBurger, 0)]),
+                Err(BurgerShopError::ZeroAmountItem)
+            );
+            assert!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap()
+                    .paid
+            );
+        }
+
+        #[ink::test]
+        fn updated_items_are_merged_before_the_limits() {
+            let mut shop = shop();
+            shop.set_max_quantity_per_item(2).unwrap();
+            set_caller(accounts().bob);
+            set_value(5);
+            let order = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::VeggieBurger, 1)])
+                .unwrap();
+
+            assert_eq!(
+                shop.update_order_items(
+                    order.order_id,
+                    vec![
+                        item(BurgerMenu::CheeseBurger, 2),
+                        item(BurgerMenu::CheeseBurger, 1),
+                    ],
+                )
+                .unwrap_err(),
+                BurgerShopError::QuantityTooHigh
+            );
+
+            let order = shop
+                .update_order_items(
+                    order.order_id,
+                    vec![
+                        item(BurgerMenu::CheeseBurger, 1),
+                        item(BurgerMenu::CheeseBurger, 1),
+                    ],
+                )
+                .unwrap();
+            assert_eq!(order.list_of_items.len(), 1);
+            assert_eq!(order.list_of_items[0].amount, 2);
+            assert_eq!(order.total_price, 24);
+        }
+
+        #[ink::test]
+        fn orders_are_split_by_payment() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let paid = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            set_value(5);
+            let reserved = shop
+                .deposit_and_reserve(vec![item(BurgerMenu::VeggieBurger, 1)])
+                .unwrap();
+            let settled = place(&mut shop, bob, vec![item(BurgerMenu::ChickenBurger, 1)]);
+
+            assert_eq!(
+                ids(shop.get_orders_by_paid(true)),
+                vec![paid.order_id, settled.order_id]
+            );
+            assert_eq!(ids(shop.get_orders_by_paid(false)), vec![reserved.order_id]);
+
+            // paying off the reservation moves it across
+            set_value(5);
+            shop.pay_balance(reserved.order_id).unwrap();
+            assert!(shop.get_orders_by_paid(false).is_empty());
+            assert_eq!(shop.get_orders_by_paid(true).len(), 3);
+        }
+
+        #[ink::test]
+        fn purging_keeps_the_average_order_value() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            set_time(1_000);
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            for order in [&first, &second] {
+                confirm(&mut shop, order);
+            }
+            set_caller(accounts().alice);
+            for _ in 0..3 {
+                shop.advance_order_status(first.order_id).unwrap();
+            }
+            assert_eq!(shop.average_order_value(), 18);
+
+            assert_eq!(shop.purge_delivered(2_000), Ok(1));
+            assert_eq!(shop.order_count(), 1);
+            assert_eq!(shop.get_total_revenue(), Ok(36));
+            assert_eq!(shop.average_order_value(), 18);
+        }
+
+        #[ink::test]
+        fn purging_only_removes_orders_delivered_before_the_cutoff() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let older = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            let newer = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            for order in [&older, &newer] {
+                confirm(&mut shop, order);
+            }
+
+            set_caller(accounts().alice);
+            set_time(1_000);
+            for _ in 0..3 {
+                shop.advance_order_status(older.order_id).unwrap();
+            }
+            set_time(3_000);
+            for _ in 0..3 {
+                shop.advance_order_status(newer.order_id).unwrap();
+            }
+
+            assert_eq!(shop.purge_delivered(2_000), Ok(1));
+            assert_eq!(
+                shop.get_single_order(older.order_id).unwrap_err(),
+                BurgerShopError::OrderNotFound
+            );
+            assert_eq!(
+                shop.get_single_order(newer.order_id).unwrap().delivered_at,
+                Some(3_000)
+            );
+            assert_eq!(shop.order_count(), 1);
+            assert_eq!(
+                ids(shop.get_orders_by_status(OrderStatus::Delivered)),
+                vec![newer.order_id]
+            );
+            assert_eq!(shop.get_total_revenue(), Ok(36));
+            assert_eq!(shop.customer_spend(bob), 36);
+            assert_eq!(shop.average_order_value(), 18);
+        }
+
+        #[ink::test]
+        fn batch_change_emits_refunded() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            set_caller(bob);
+            set_value(30);
+            let orders = shop
+                .take_orders_batch(vec![
+                    vec![item(BurgerMenu::CheeseBurger, 1)],
+                    vec![item(BurgerMenu::VeggieBurger, 1)],
+                ])
+                .unwrap();
+
+            let refunded: Vec<Refunded> = events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::Refunded(refunded) => Some(refunded),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(refunded.len(), 1);
+            assert_eq!(refunded[0].order_id, orders[1].order_id);
+            assert_eq!(refunded[0].customer, bob);
+            assert_eq!(refunded[0].amount, 8);
+        }
+
+        #[ink::test]
+        fn cancelling_emits_refunded() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 1)]);
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            shop.cancel_order(order.order_id).unwrap();
+
+            let refunded: Vec<Refunded> = events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::Refunded(refunded) => Some(refunded),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(refunded.len(), 1);
+            assert_eq!(refunded[0].order_id, order.order_id);
+            assert_eq!(refunded[0].customer, bob);
+            assert_eq!(refunded[0].amount, 24);
+        }
+
+        #[ink::test]
+        fn partial_refunds_emit_refunded() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            set_caller(accounts().alice);
+            shop.partial_refund(order.order_id, 5).unwrap();
+
+            let refunded: Vec<Refunded> = events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::Refunded(refunded) => Some(refunded),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(refunded.len(), 1);
+            assert_eq!(refunded[0].order_id, order.order_id);
+            assert_eq!(refunded[0].customer, bob);
+            assert_eq!(refunded[0].amount, 5);
+        }
+
+        #[ink::test]
+        fn each_burger_is_capped_at_the_quantity_limit() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_max_quantity_per_item(3).unwrap();
+
+            // the cap is per burger, not per order
+            let order = place(
+                &mut shop,
+                bob,
+                vec![
+                    item(BurgerMenu::CheeseBurger, 3),
+                    item(BurgerMenu::VeggieBurger, 3),
+                ],
+            );
+            assert!(order.paid);
+
+            set_value(48);
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 4)])
+                    .unwrap_err(),
+                BurgerShopError::QuantityTooHigh
+            );
+
+            set_caller(accounts().alice);
+            shop.set_max_quantity_per_item(4).unwrap();
+            assert!(place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 4)]).paid);
+
+            set_caller(bob);
+            assert_eq!(
+                shop.set_max_quantity_per_item(10),
+                Err(BurgerShopError::NotOwner)
+            );
+        }
+
+        #[ink::test]
+        fn status_summary_counts_orders_per_status() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let first = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            let second = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+            set_value(5);
+            shop.deposit_and_reserve(vec![item(BurgerMenu::CheeseBurger, 1)])
+                .unwrap();
+
+            set_caller(accounts().alice);
+            shop.advance_order_status(first.order_id).unwrap();
+            assert_eq!(
+                shop.status_summary(),
+                vec![
+                    (OrderStatus::Reserved, 1),
+                    (OrderStatus::Paid, 1),
+                    (OrderStatus::Preparing, 1),
+                    (OrderStatus::Ready, 0),
+                    (OrderStatus::Delivered, 0),
+                ]
+            );
+
+            set_caller(bob);
+            shop.cancel_order(second.order_id).unwrap();
+            assert_eq!(
+                shop.status_summary(),
+                vec![
+                    (OrderStatus::Reserved, 1),
+                    (OrderStatus::Paid, 0),
+                    (OrderStatus::Preparing, 1),
+                    (OrderStatus::Ready, 0),
+                    (OrderStatus::Delivered, 0),
+                ]
+            );
+        }
+
+        #[ink::test]
+        fn stock_falling_to_the_reorder_point_raises_one_alert() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 5)
+                .unwrap();
+            shop.set_reorder_point(MAIN_OUTLET, BurgerMenu::CheeseBurger, 2)
+                .unwrap();
+
+            place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+            place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]);
+
+            // only the order crossing the reorder point alerts
+            let alerts: Vec<LowStockAlert> = events()
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::LowStockAlert(alert) => Some(alert),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(alerts.len(), 1);
+            assert_eq!(alerts[0].outlet, MAIN_OUTLET);
+            assert_eq!(alerts[0].menu, BurgerMenu::CheeseBurger);
+            assert_eq!(alerts[0].remaining, 1);
+        }
+
+        #[ink::test]
+        fn version_reports_the_live_logic() {
+            let shop = shop();
+            assert_eq!(shop.version(), VERSION);
+        }
+
+        #[ink::test]
+        fn refunds_can_be_switched_off() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            let order = place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 2)]);
+
+            set_caller(accounts().alice);
+            shop.set_refunds_enabled(false).unwrap();
+            assert_eq!(
+                shop.partial_refund(order.order_id, 5),
+                Err(BurgerShopError::RefundsDisabled)
+            );
+            set_caller(bob);
+            assert_eq!(
+                shop.cancel_order(order.order_id),
+                Err(BurgerShopError::RefundsDisabled)
+            );
+
+            // the rest of the shop keeps running
+            let other = place(&mut shop, bob, vec![item(BurgerMenu::VeggieBurger, 1)]);
+            set_caller(accounts().alice);
+            assert_eq!(
+                shop.advance_order_status(other.order_id),
+                Ok(OrderStatus::Preparing)
+            );
+
+            shop.set_refunds_enabled(true).unwrap();
+            let before = balance_of(bob);
+            set_caller(bob);
+            assert_eq!(shop.cancel_order(order.order_id), Ok(()));
+            assert_eq!(balance_of(bob), before + 24);
+        }
+
+        #[ink::test]
+        fn surge_pricing_rises_with_pending_orders() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.set_surge_pricing(1_000, 2_000).unwrap();
+            let items = vec![item(BurgerMenu::VeggieBurger, 10)];
+
+            let mut totals = Vec::new();
+            for _ in 0..4 {
+                let estimate = shop.estimate_order_price(items.clone()).unwrap();
+                let order = place(&mut shop, bob, items.clone());
+                assert_eq!(order.paid_amount, estimate);
+                totals.push(order.total_price);
+            }
+            assert_eq!(totals, vec![100, 110, 120, 120]);
+
+            // delivered orders no longer count as pending
+            set_caller(accounts().alice);
+            for id in 0..3 {
+                for _ in 0..3 {
+                    shop.advance_order_status(id).unwrap();
+                }
+            }
+            assert_eq!(shop.estimate_order_price(items), Ok(110));
+        }
+
+        #[ink::test]
+        fn item_count_merges_duplicate_lines() {
+            let mut shop = shop();
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![
+                    item(BurgerMenu::CheeseBurger, 2),
+                    item(BurgerMenu::VeggieBurger, 1),
+                    item(BurgerMenu::CheeseBurger, 3),
+                ],
+            );
+
+            assert_eq!(shop.order_item_count(order.order_id), Ok((2, 6)));
+            assert_eq!(
+                shop.order_item_count(order.order_id + 1),
+                Err(BurgerShopError::OrderNotFound)
+            );
+        }
+
+        #[ink::test]
+        fn banned_customers_cannot_order_until_unbanned() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.ban(bob).unwrap();
+            set_caller(bob);
+            set_value(12);
+
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::Banned
+            );
+
+            set_caller(accounts().alice);
+            shop.unban(bob).unwrap();
+            assert!(place(&mut shop, bob, vec![item(BurgerMenu::CheeseBurger, 1)]).paid);
+        }
+
+        #[ink::test]
+        fn bans_override_the_allowlist() {
+            let mut shop = shop();
+            let bob = accounts().bob;
+            shop.allow(bob).unwrap();
+            shop.set_allowlist_enabled(true).unwrap();
+            shop.ban(bob).unwrap();
+            set_caller(bob);
+            set_value(12);
+
+            assert_eq!(
+                shop.take_order_and_payment(vec![item(BurgerMenu::CheeseBurger, 1)])
+                    .unwrap_err(),
+                BurgerShopError::Banned
+            );
+        }
+
+        #[ink::test]
+        fn an_empty_shop_can_take_orders() {
+            let shop_account = AccountId::from(SHOP);
+            test::set_callee::<DefaultEnvironment>(shop_account);
+            test::set_account_balance::<DefaultEnvironment>(shop_account, 0);
+            let mut shop = BurgerShop::new_with_multiplier(1).unwrap();
+
+            let order = place(
+                &mut shop,
+                accounts().bob,
+                vec![item(BurgerMenu::CheeseBurger, 1)],
+            );
+            assert!(order.paid);
+            assert_eq!(order.paid_amount, 12);
+        }
+    }
+
+    /// End-to-end tests against a running node, for the cross-contract token
+    /// calls the off-chain environment can't make
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use ink_e2e::build_message;
+        use mock_psp22::MockPsp22Ref;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        fn cheeseburger() -> Vec<FoodItem> {
+            vec![FoodItem {
+                burger_menu: BurgerMenu::CheeseBurger,
+                amount: 1,
+            }]
+        }
+
+        #[ink_e2e::test]
+        async fn token_payment_fails_without_a_token_contract(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            // an account with no contract behind it can't move tokens
+            let token = ink_e2e::account_id(ink_e2e::AccountKeyring::Eve);
+            let shop = client
+                .instantiate(
+                    "burger_shop",
+                    &ink_e2e::alice(),
+                    BurgerShopRef::new_with_payment_token(token),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            let order = build_message::<BurgerShopRef>(shop)
+                .call(|shop| shop.take_order_and_payment_psp22(cheeseburger()));
+            let result = client.call_dry_run(&ink_e2e::bob(), &order, 0, None).await;
+            assert_eq!(
+                result.return_value().unwrap_err(),
+                BurgerShopError::TokenTransferFailed
+            );
+
+            Ok(())
+        }
+
+        #[ink_e2e::test(additional_contracts = "mock_psp22/Cargo.toml")]
+        async fn token_payment_pulls_tokens_from_the_customer(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let token = client
+                .instantiate(
+                    "mock_psp22",
+                    &ink_e2e::bob(),
+                    MockPsp22Ref::new(100 * DEFAULT_PRICE_MULTIPLIER),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let shop = client
+                .instantiate(
+                    "burger_shop",
+                    &ink_e2e::alice(),
+                    BurgerShopRef::new_with_payment_token(token),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            let order = build_message::<BurgerShopRef>(shop)
+                .call(|shop| shop.take_order_and_payment_psp22(cheeseburger()));
+            client
+                .call(&ink_e2e::bob(), order, 0, None)
+                .await
+                .expect("order failed");
+
+            let balance = build_message::<MockPsp22Ref>(token).call(|token| token.balance_of(shop));
+            let balance = client
+                .call_dry_run(&ink_e2e::bob(), &balance, 0, None)
+                .await
+                .return_value();
+            assert_eq!(balance, 12 * DEFAULT_PRICE_MULTIPLIER);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn underpaid_batch_is_reverted(mut client: ink_e2e::Client<C, E>) -> E2EResult<()> {
+            let shop = client
+                .instantiate(
+                    "burger_shop",
+                    &ink_e2e::alice(),
+                    BurgerShopRef::new(),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            // one unit short of the two orders together
+            let batch = build_message::<BurgerShopRef>(shop).call(|shop| {
+                shop.take_orders_batch(vec![
+                    cheeseburger(),
+                    vec![FoodItem {
+                        burger_menu: BurgerMenu::ChickenBurger,
+                        amount: 1,
+                    }],
+                ])
+            });
+            let payment = 27 * DEFAULT_PRICE_MULTIPLIER - 1;
+            assert!(client
+                .call(&ink_e2e::bob(), batch, payment, None)
+                .await
+                .is_err());
+
+            let count = build_message::<BurgerShopRef>(shop).call(|shop| shop.order_count());
+            let count = client
+                .call_dry_run(&ink_e2e::bob(), &count, 0, None)
+                .await
+                .return_value();
+            assert_eq!(count, 0);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test(additional_contracts = "mock_psp22/Cargo.toml")]
+        async fn out_of_stock_token_order_keeps_the_customers_tokens(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let supply = 100 * DEFAULT_PRICE_MULTIPLIER;
+            let token = client
+                .instantiate(
+                    "mock_psp22",
+                    &ink_e2e::bob(),
+                    MockPsp22Ref::new(supply),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+            let shop = client
+                .instantiate(
+                    "burger_shop",
+                    &ink_e2e::alice(),
+                    BurgerShopRef::new_with_payment_token(token),
+                    0,
+                    None,
+                )
+                .await
+                .expect("instantiate failed")
+                .account_id;
+
+            // cheeseburgers are sold out, so the order fails and the tokens stay
+            // with the customer
+            let restock = build_message::<BurgerShopRef>(shop)
+                .call(|shop| shop.restock(MAIN_OUTLET, BurgerMenu::CheeseBurger, 0));
+            client
+                .call(&ink_e2e::alice(), restock, 0, None)
+                .await
+                .expect("restock failed");
+
+            let order = build_message::<BurgerShopRef>(shop)
+                .call(|shop| shop.take_order_and_payment_psp22(cheeseburger()));
+            let result = client.call_dry_run(&ink_e2e::bob(), &order, 0, None).await;
+            assert_eq!(
+                result.return_value().unwrap_err(),
+                BurgerShopError::OutOfStock {
+                    menu: BurgerMenu::CheeseBurger
+                }
+            );
+            assert!(client.call(&ink_e2e::bob(), order, 0, None).await.is_err());
+
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let balance = build_message::<MockPsp22Ref>(token).call(|token| token.balance_of(bob));
+            let balance = client
+                .call_dry_run(&ink_e2e::bob(), &balance, 0, None)
+                .await
+                .return_value();
+            assert_eq!(balance, supply);
+
+            Ok(())
         }
     }
 }
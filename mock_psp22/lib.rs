@@ -0,0 +1,121 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+pub use self::mock_psp22::MockPsp22Ref;
+
+/// Bare-bones PSP22 token for the burger shop's end-to-end tests. Allowances
+/// aren't checked, and transfers to a blocked account always fail.
+#[ink::contract]
+pub mod mock_psp22 {
+
+    use ink::prelude::{string::String, vec::Vec};
+    use ink::storage::Mapping;
+
+    /// Errors returned by a PSP22 token contract
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Psp22Error {
+        Custom(String),
+        InsufficientBalance,
+        InsufficientAllowance,
+        ZeroRecipientAddress,
+        ZeroSenderAddress,
+        SafeTransferCheckFailed(String),
+    }
+
+    /// The PSP22 messages the shop calls
+    #[ink::trait_definition]
+    pub trait PSP22 {
+        /// Move tokens from the caller to `to`
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<(), Psp22Error>;
+
+        /// Move tokens from `from` to `to` on the caller's behalf
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<(), Psp22Error>;
+    }
+
+    /// Contract storage for the mock token
+    #[ink(storage)]
+    pub struct MockPsp22 {
+        balances: Mapping<AccountId, Balance>,
+        blocked: Mapping<AccountId, ()>,
+    }
+
+    impl MockPsp22 {
+        /// Mint the whole supply to the deployer
+        #[ink(constructor)]
+        pub fn new(supply: Balance) -> Self {
+            let mut balances = Mapping::new();
+            balances.insert(Self::env().caller(), &supply);
+            Self {
+                balances,
+                blocked: Mapping::new(),
+            }
+        }
+
+        /// Get the token balance of an account
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or(0)
+        }
+
+        /// Make every transfer to an account fail
+        #[ink(message)]
+        pub fn block(&mut self, account: AccountId) {
+            self.blocked.insert(account, &());
+        }
+
+        /// Move tokens between two accounts
+        fn move_tokens(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<(), Psp22Error> {
+            if self.blocked.contains(to) {
+                return Err(Psp22Error::Custom(String::from("blocked")));
+            }
+            let from_balance = self.balance_of(from);
+            if from_balance < value {
+                return Err(Psp22Error::InsufficientBalance);
+            }
+            self.balances.insert(from, &(from_balance - value));
+            self.balances.insert(to, &(self.balance_of(to) + value));
+            Ok(())
+        }
+    }
+
+    impl PSP22 for MockPsp22 {
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), Psp22Error> {
+            self.move_tokens(self.env().caller(), to, value)
+        }
+
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), Psp22Error> {
+            self.move_tokens(from, to, value)
+        }
+    }
+}